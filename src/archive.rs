@@ -0,0 +1,185 @@
+//! Generating the statics file from a tar archive, eg. a prebuilt frontend
+//! bundle, without extracting it by hand first.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+
+use crate::{generate_to, Config, Generator, Report};
+
+/// The first bytes of a gzip stream.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+impl Config {
+    /// Generate the statics file at `out_path` from the tar archive read
+    /// from `archive`, which may be gzipped. Each entry's path in the
+    /// archive stands for its path relative to an asset directory, so an
+    /// entry `app/main.js` becomes `app::main_js`.
+    ///
+    /// Nothing is unpacked, so the entries' content is embedded and this
+    /// needs [`Config::embed`]. Options that look at files on disk,
+    /// [`Config::serve_root`], [`Config::modified_after`] and
+    /// [`Config::modified_before`], are refused. Entries other than files
+    /// are skipped.
+    pub fn codegen_archive(&self, out_path: &Path, archive: impl Read) -> std::io::Result<Report> {
+        if !self.embed && !self.compress_embedded_enabled() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "archive entries need embedded content, see Config::embed",
+            ));
+        }
+        let needing_disk = [
+            ("Config::serve_root", self.serve_root),
+            ("Config::modified_after", self.modified_after.is_some()),
+            ("Config::modified_before", self.modified_before.is_some()),
+        ];
+        if let Some((option, _)) = needing_disk.iter().find(|(_, enabled)| *enabled) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "{option} needs files on disk, which Config::codegen_archive doesn't unpack"
+                ),
+            ));
+        }
+
+        let entries = read_entries(archive)?;
+        let mut code = Vec::new();
+        let generator = Generator::new(self, Some(out_path), Some(&entries))?;
+        let output = generate_to(&mut code, generator, &[PathBuf::new()], &[])?;
+        let code = String::from_utf8(code).expect("generated code is UTF-8");
+        self.write_generated(out_path, &code, output)
+    }
+}
+
+/// The content of each file in the tar archive `archive`, by its path in it.
+fn read_entries(archive: impl Read) -> std::io::Result<BTreeMap<PathBuf, Vec<u8>>> {
+    let mut archive = BufReader::new(archive);
+    let gzipped = archive.fill_buf()?.starts_with(&GZIP_MAGIC);
+    let archive: Box<dyn Read> = if gzipped {
+        Box::new(flate2::read::GzDecoder::new(archive))
+    } else {
+        Box::new(archive)
+    };
+
+    let mut entries = BTreeMap::new();
+    let mut archive = tar::Archive::new(archive);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        // Paths like `./app/main.js` are the same as `app/main.js`, while
+        // those escaping the archive, like `../etc/passwd`, are refused.
+        let path = entry.path()?.into_owned();
+        let mut logical_path = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::Normal(part) => logical_path.push(part),
+                Component::CurDir => {}
+                _ => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("archive entry {} is outside the archive", path.display()),
+                    ))
+                }
+            }
+        }
+
+        let mut content = Vec::with_capacity(entry.size() as usize);
+        entry.read_to_end(&mut content)?;
+        entries.insert(logical_path, content);
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn tar(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, content) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, *content).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_codegen_archive() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+        let config = Config::new().embed(true);
+
+        let archive = tar(&[("./app/main.js", b"main"), ("index.html", b"<html>")]);
+        config
+            .codegen_archive(&out_path, archive.as_slice())
+            .unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains("pub mod app {"));
+        assert!(generated.contains(&format!(
+            "name: \"/static/app/main-{:x}.js\"",
+            md5::compute(b"main")
+        )));
+        assert!(generated.contains("/// From \"app/main.js\""));
+        assert!(generated.contains("pub static index_html: StaticFile"));
+        assert!(generated.contains("content: b\"<html>\""));
+        assert!(!generated.contains("include_bytes!"));
+
+        // A later archive replaces the earlier one's statics, and neither is
+        // unpacked.
+        let mut gzipped = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+        std::io::Write::write_all(&mut gzipped, &tar(&[("app.css", b"body {}")])).unwrap();
+        config
+            .codegen_archive(&out_path, gzipped.finish().unwrap().as_slice())
+            .unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains("pub static app_css: StaticFile"));
+        assert!(!generated.contains("index_html"));
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_codegen_archive_refusals() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+        let archive = tar(&[("index.html", b"<html>")]);
+
+        let err = Config::new()
+            .codegen_archive(&out_path, archive.as_slice())
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+        let err = Config::new()
+            .embed(true)
+            .serve_root(true)
+            .codegen_archive(&out_path, archive.as_slice())
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(err.to_string().starts_with("Config::serve_root"));
+
+        // `tar::Builder` refuses `..` itself, so the header is written by hand.
+        let mut header = tar::Header::new_gnu();
+        header.as_gnu_mut().unwrap().name[..9].copy_from_slice(b"../passwd");
+        header.set_size(0);
+        header.set_mode(0o644);
+        header.set_cksum();
+        let mut escaping = header.as_bytes().to_vec();
+        escaping.resize(escaping.len() + 1024, 0);
+        let err = Config::new()
+            .embed(true)
+            .codegen_archive(&out_path, escaping.as_slice())
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(!out_path.exists());
+    }
+}
@@ -0,0 +1,73 @@
+//! Minimal glob matching for selecting assets in [`Config`](crate::Config).
+//!
+//! Patterns are matched against a file's path relative to its asset
+//! directory, using `/` as the separator. `*` matches anything but `/`, `?`
+//! matches a single character other than `/`, and `**` matches across
+//! directories, so `fonts/**` matches every file under `fonts`. A pattern
+//! without a `/`, like `*.pdf`, is matched against the file name alone.
+
+pub(crate) fn matches(pattern: &str, path: &str) -> bool {
+    let path = if pattern.contains('/') {
+        path
+    } else {
+        path.rsplit('/').next().unwrap_or(path)
+    };
+
+    let pattern: Vec<char> = pattern.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    matches_at(&pattern, &path)
+}
+
+fn matches_at(pattern: &[char], path: &[char]) -> bool {
+    match pattern {
+        [] => path.is_empty(),
+        ['*', '*', rest @ ..] => {
+            if let ['/', after_slash @ ..] = rest {
+                if matches_at(after_slash, path) {
+                    return true;
+                }
+            }
+            (0..=path.len()).any(|i| matches_at(rest, &path[i..]))
+        }
+        ['*', rest @ ..] => {
+            let segment_end = path.iter().position(|&c| c == '/').unwrap_or(path.len());
+            (0..=segment_end).any(|i| matches_at(rest, &path[i..]))
+        }
+        ['?', rest @ ..] => match path {
+            [c, path_rest @ ..] if *c != '/' => matches_at(rest, path_rest),
+            _ => false,
+        },
+        [p, rest @ ..] => match path {
+            [c, path_rest @ ..] if c == p => matches_at(rest, path_rest),
+            _ => false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_literal_and_wildcards() {
+        assert!(matches("app.js", "app.js"));
+        assert!(matches("vendor/app.js", "vendor/app.js"));
+        assert!(!matches("vendor/app.js", "app.js"));
+        assert!(matches("*.js", "app.js"));
+        assert!(matches("*.js", "vendor/app.js"));
+        assert!(!matches("*.js", "app.css"));
+        assert!(matches("app.?s", "app.js"));
+        assert!(!matches("vendor/*.js", "vendor/lib/app.js"));
+    }
+
+    #[test]
+    fn test_double_star() {
+        assert!(matches("fonts/**", "fonts/a.woff2"));
+        assert!(matches("fonts/**", "fonts/nested/a.woff2"));
+        assert!(!matches("fonts/**", "images/a.png"));
+        assert!(matches("**/*.map", "app.js.map"));
+        assert!(matches("**/*.map", "vendor/lib/app.js.map"));
+        assert!(matches("docs/**/index.html", "docs/index.html"));
+        assert!(matches("docs/**/index.html", "docs/a/b/index.html"));
+    }
+}
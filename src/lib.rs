@@ -1,416 +1,8862 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::{self, File};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[cfg(feature = "archive")]
+mod archive;
+mod glob;
+#[cfg(feature = "macros")]
+mod macros;
+mod manifest;
+mod proxy;
+#[cfg(feature = "watch")]
+mod watch;
+
+pub use manifest::{Manifest, ManifestDiff};
+pub use proxy::ProxyServer;
+
+/// What to do with files whose extension has no known MIME type.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum MimeFallback {
+    /// Serve them as `application/octet-stream`.
+    #[default]
+    OctetStream,
+    /// Serve them with the given MIME type, eg. `text/plain`.
+    Custom(String),
+    /// Fail code generation, naming the offending file.
+    Error,
+}
+
+/// The digest used to fingerprint file contents.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    #[default]
+    Md5,
+    Sha256,
+    /// BLAKE3 with its default 32 byte output, behind the `blake3` feature.
+    #[cfg(feature = "blake3")]
+    Blake3,
+}
+
+/// A streaming digest supplied with [`Config::hasher`], for fingerprinting
+/// URLs with an algorithm `cacheb` doesn't provide, eg. xxHash.
+pub trait ContentHash {
+    /// Feed the next chunk of the file into the digest.
+    fn update(&mut self, data: &[u8]);
+
+    /// The digest of everything fed in so far.
+    fn finalize(self: Box<Self>) -> Vec<u8>;
+}
+
+/// An in-progress digest for one [`HashAlgorithm`] or [`ContentHash`].
+enum Hasher {
+    Md5(md5::Context),
+    Sha256(sha2::Sha256),
+    #[cfg(feature = "blake3")]
+    Blake3(Box<blake3::Hasher>),
+    Custom(Box<dyn ContentHash>),
+}
+
+impl Hasher {
+    fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Md5 => Hasher::Md5(md5::Context::new()),
+            HashAlgorithm::Sha256 => Hasher::Sha256(sha2::Digest::new()),
+            #[cfg(feature = "blake3")]
+            HashAlgorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Md5(context) => context.consume(data),
+            Hasher::Sha256(hasher) => sha2::Digest::update(hasher, data),
+            #[cfg(feature = "blake3")]
+            Hasher::Blake3(hasher) => {
+                hasher.update(data);
+            }
+            Hasher::Custom(hasher) => hasher.update(data),
+        }
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        match self {
+            Hasher::Md5(context) => context.compute().0.to_vec(),
+            Hasher::Sha256(hasher) => sha2::Digest::finalize(hasher).to_vec(),
+            #[cfg(feature = "blake3")]
+            Hasher::Blake3(hasher) => hasher.finalize().as_bytes().to_vec(),
+            Hasher::Custom(hasher) => hasher.finalize(),
+        }
+    }
+}
+
+/// How file hashes are encoded into URLs. All encodings are URL-safe.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HashEncoding {
+    /// Lowercase hexadecimal, 32 characters for MD5.
+    #[default]
+    Hex,
+    /// Uppercase hexadecimal, for URL schemes that expect `D41D8CD9...`.
+    UpperHex,
+    /// Lowercase, unpadded RFC 4648 base32, 26 characters for MD5.
+    Base32,
+    /// Base62 using `0-9A-Za-z`, zero-padded to 22 characters for MD5.
+    Base62,
+}
+
+impl HashEncoding {
+    fn encode(self, bytes: &[u8]) -> String {
+        match self {
+            HashEncoding::Hex => bytes.iter().map(|byte| format!("{:02x}", byte)).collect(),
+            HashEncoding::UpperHex => bytes.iter().map(|byte| format!("{:02X}", byte)).collect(),
+            HashEncoding::Base32 => {
+                const ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+                let mut encoded = String::new();
+                for chunk in bytes.chunks(5) {
+                    let mut buffer = [0u8; 8];
+                    buffer[3..3 + chunk.len()].copy_from_slice(chunk);
+                    let bits = u64::from_be_bytes(buffer);
+
+                    let chars = (chunk.len() * 8).div_ceil(5);
+                    for i in 0..chars {
+                        let index = (bits >> (35 - i * 5)) & 0x1f;
+                        encoded.push(ALPHABET[index as usize] as char);
+                    }
+                }
+                encoded
+            }
+            HashEncoding::Base62 => {
+                const ALPHABET: &[u8; 62] =
+                    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+                let width = (bytes.len() as f64 * 8.0 / 62f64.log2()).ceil() as usize;
+                let mut number = bytes.to_vec();
+                let mut digits = Vec::with_capacity(width);
+
+                while number.iter().any(|&byte| byte != 0) {
+                    let mut remainder = 0u32;
+                    for byte in number.iter_mut() {
+                        let value = (remainder << 8) | u32::from(*byte);
+                        *byte = (value / 62) as u8;
+                        remainder = value % 62;
+                    }
+                    digits.push(ALPHABET[remainder as usize]);
+                }
+                digits.resize(width.max(digits.len()), b'0');
+
+                digits.iter().rev().map(|&digit| digit as char).collect()
+            }
+        }
+    }
+}
+
+/// Where the hash goes in each file's URL.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HashPlacement {
+    /// Before the extension, eg. `/static/app/main-1a2b3c.js`.
+    #[default]
+    FileName,
+    /// As the leading path segment, keeping the file name intact, eg.
+    /// `/static/1a2b3c/app/main.js`.
+    Directory,
+}
+
+/// Whether URLs are absolute paths or relative to the current document.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UrlForm {
+    /// From the root of the site, eg. `/static/app-1a2b3c.js`.
+    #[default]
+    Absolute,
+    /// Without the leading slash, eg. `static/app-1a2b3c.js`.
+    Relative,
+    /// Starting with `./`, eg. `./static/app-1a2b3c.js`.
+    DotRelative,
+}
+
+/// How the identifier of each static is derived from its file name.
+#[derive(Debug, Clone, Copy, Default)]
+pub enum IdentStrategy {
+    /// The whole file name, eg. `jquery_min_js` for `jquery.min.js`.
+    #[default]
+    FileName,
+    /// The file name without its extension, eg. `jquery_min`.
+    FileStem,
+    /// A custom function of the file name.
+    Custom(fn(&str) -> String),
+}
+
+/// A `StaticFile` field that [`Config::fields`] can leave out. `name` and
+/// `mime` are always generated, and the rest come with the options that add
+/// them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    /// The file's canonical path on disk, `file_name`.
+    FileName,
+    /// The [`Config::meta`] pairs, `meta`.
+    Meta,
+    /// The `Cache-Control` value, `cache_control`.
+    CacheControl,
+}
+
+/// A derive [`Config::derives`] can add to `StaticFile`'s `Debug`.
+/// `PartialEq`, `Eq` and `Hash` are always implemented, by `name`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Derive {
+    /// `Clone`, eg. to build modified copies of statics.
+    Clone,
+    /// `Copy`. Needs [`Derive::Clone`], and can't be derived with
+    /// `Config::compress_embedded`, whose decompressed content is cached in
+    /// each static.
+    Copy,
+    /// `Default`, with empty strings and content.
+    Default,
+}
+
+/// What [`Config::codegen`] noticed while generating, for things worth
+/// reporting that shouldn't fail the build.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct Report {
+    /// Files over the [`warn_size`](Config::warn_size), with their sizes in
+    /// bytes.
+    pub large_files: Vec<(PathBuf, u64)>,
+    /// Files whose names end in dots or spaces, which Windows strips. Their
+    /// URLs and identifiers use the stripped name on every platform.
+    pub unportable_names: Vec<PathBuf>,
+    /// Pairs of files whose paths differ only by case, so they'd collide on
+    /// case-insensitive filesystems like macOS' and Windows' defaults.
+    pub case_collisions: Vec<(PathBuf, PathBuf)>,
+    /// Files whose content hash changed since the run of the report passed
+    /// to [`Config::codegen_since`], eg. to reload a dev server's pages.
+    /// `None` from other runs.
+    pub changes: Option<ManifestDiff>,
+    /// The hex encoded URL hash digest of every file by its relative path,
+    /// for comparing a later run against.
+    hashes: Manifest,
+}
+
+/// Options for generating the statics file.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    mime_fallback: MimeFallback,
+    sniff_mime: bool,
+    extension_mimes: Vec<(String, String)>,
+    compressible_extensions: Vec<(String, bool)>,
+    glob_mimes: Vec<(String, String)>,
+    cfgs: Vec<(PathBuf, String)>,
+    dependencies: Vec<(PathBuf, PathBuf)>,
+    url_prefix: Option<String>,
+    url_prefixes: Vec<(PathBuf, String)>,
+    public_url_prefix: Option<String>,
+    salt: String,
+    hash_buffer_size: Option<usize>,
+    normalize_line_endings: bool,
+    index_files: bool,
+    hash_encoding: HashEncoding,
+    hash_placement: HashPlacement,
+    url_form: UrlForm,
+    warn_size: Option<u64>,
+    served_extensions: Vec<(String, String)>,
+    flat: bool,
+    overlay: bool,
+    logical_paths: Vec<(PathBuf, PathBuf)>,
+    reexport: bool,
+    categories: bool,
+    dir_hashes: bool,
+    cache_controls: Vec<(String, String)>,
+    unhashed: Vec<String>,
+    fixed_paths: Vec<String>,
+    downloads: Vec<String>,
+    preloads: Vec<String>,
+    hash_length: Option<usize>,
+    generated_at: bool,
+    header: Option<String>,
+    meta: Vec<(String, String, String)>,
+    doc_template: Option<String>,
+    deny_case_collisions: bool,
+    const_refs: bool,
+    asset_enum: bool,
+    no_std: bool,
+    strip_prefix: Option<PathBuf>,
+    hash_algorithm: HashAlgorithm,
+    hasher: Option<fn() -> Box<dyn ContentHash>>,
+    etag: Option<HashAlgorithm>,
+    etag_uppercase: bool,
+    alternates: bool,
+    source_maps: bool,
+    rewrite_source_map_urls: bool,
+    match_lookup: bool,
+    lazy_lookup: bool,
+    resolve: bool,
+    content_hashes: bool,
+    manifest_sizes: bool,
+    url_macro: bool,
+    fallback: Option<PathBuf>,
+    not_found: Option<(String, Vec<u8>)>,
+    routes: bool,
+    ident_strategy: IdentStrategy,
+    static_file_path: Option<String>,
+    base_url_env: Option<String>,
+    serve_root: bool,
+    kinds: bool,
+    include_hidden: bool,
+    fields: Option<Vec<Field>>,
+    derives: Vec<Derive>,
+    modified_after: Option<SystemTime>,
+    modified_before: Option<SystemTime>,
+    embed: bool,
+    relative_includes: bool,
+    hoist_bytes: bool,
+    #[cfg(feature = "gzip")]
+    gzip: bool,
+    #[cfg(feature = "brotli")]
+    brotli: bool,
+    #[cfg(feature = "brotli")]
+    compress_embedded: bool,
+    #[cfg(feature = "axum")]
+    axum: bool,
+    #[cfg(feature = "rocket")]
+    rocket: bool,
+    #[cfg(feature = "phf")]
+    phf_lookup: bool,
+    #[cfg(feature = "http")]
+    http: bool,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the MIME type policy for unknown extensions.
+    pub fn mime_fallback(mut self, fallback: MimeFallback) -> Self {
+        self.mime_fallback = fallback;
+        self
+    }
+
+    /// Serve files with `extension`, matched case-insensitively, as `mime`,
+    /// overriding the built-in table.
+    pub fn mime_for_extension(
+        mut self,
+        extension: impl Into<String>,
+        mime: impl Into<String>,
+    ) -> Self {
+        self.extension_mimes.push((extension.into(), mime.into()));
+        self
+    }
+
+    /// Serve files matching the glob `pattern` as `mime`, eg. `fonts/**` as
+    /// `font/woff2`. Takes precedence over [`Config::mime_for_extension`],
+    /// and earlier patterns over later ones.
+    pub fn mime_for_glob(mut self, pattern: impl Into<String>, mime: impl Into<String>) -> Self {
+        self.glob_mimes.push((pattern.into(), mime.into()));
+        self
+    }
+
+    /// The MIME type configured for a file at `rel_path` with `extension`,
+    /// if any.
+    fn mime_override(&self, rel_path: &str, extension: &str) -> Option<&str> {
+        let by_glob = self
+            .glob_mimes
+            .iter()
+            .find(|(pattern, _)| glob::matches(pattern, rel_path));
+        let by_extension = || {
+            self.extension_mimes
+                .iter()
+                .find(|(ext, _)| ext.eq_ignore_ascii_case(extension))
+        };
+        by_glob.or_else(by_extension).map(|(_, mime)| mime.as_str())
+    }
+
+    /// Detect the MIME type of files from their first bytes, eg. the PNG
+    /// signature, when their extension isn't [overridden](Config::mime_for_glob).
+    /// Extensionless or mislabelled files get the right type, and files
+    /// matching no signature fall back to their extension's. Recognizes PNG,
+    /// JPEG, GIF, WebP, AVIF, PDF, WebAssembly, WOFF, WOFF2, ZIP and gzip.
+    pub fn sniff_mime(mut self, enabled: bool) -> Self {
+        self.sniff_mime = enabled;
+        self
+    }
+
+    /// Only compile files under `path` when the cfg `predicate` holds, eg.
+    /// `feature = "admin"`. `path` may be an asset directory, one of its
+    /// subdirectories or a single file.
+    pub fn cfg(mut self, path: impl Into<PathBuf>, predicate: impl Into<String>) -> Self {
+        self.cfgs.push((path.into(), predicate.into()));
+        self
+    }
+
+    /// Mix the contents of `dependency` into the URL hash of the file at
+    /// `entry`, eg. a stylesheet it `@import`s, so the entry's URL changes
+    /// whenever either does. Only direct dependencies count; list each file
+    /// an entry relies on, however indirectly.
+    pub fn dependency(mut self, entry: impl Into<PathBuf>, dependency: impl Into<PathBuf>) -> Self {
+        self.dependencies.push((entry.into(), dependency.into()));
+        self
+    }
+
+    /// The declared dependencies of the file at `path`.
+    fn dependencies_of<'a>(&'a self, path: &'a Path) -> impl Iterator<Item = &'a Path> {
+        self.dependencies
+            .iter()
+            .filter(move |(entry, _)| {
+                path_within(path, entry).is_some_and(|rel_path| rel_path.as_os_str().is_empty())
+            })
+            .map(|(_, dependency)| dependency.as_path())
+    }
+
+    /// Serve files under `prefix` instead of `/static/`, eg. `/assets/`.
+    pub fn url_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.url_prefix = Some(prefix.into());
+        self
+    }
+
+    /// Serve files under `path`, eg. one of several asset directories,
+    /// under `prefix` instead of the [`Config::url_prefix`]. The first
+    /// `path` containing a file wins.
+    pub fn dir_url_prefix(mut self, path: impl Into<PathBuf>, prefix: impl Into<String>) -> Self {
+        self.url_prefixes.push((path.into(), prefix.into()));
+        self
+    }
+
+    /// Advertise files under `prefix` while still routing them under the
+    /// [`Config::url_prefix`], for a reverse proxy that rewrites one to the
+    /// other, eg. `/app/static/` to `/static/`. Every static then has a
+    /// `route` field, the URL `get` matches, and a `url` field, the one
+    /// `Display`, `static_url!` and `resolve` give for pages to link to.
+    pub fn public_url_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.public_url_prefix = Some(prefix.into());
+        self
+    }
+
+    /// `url`, as routed for `path`, under the public URL prefix instead.
+    fn public_url(&self, path: &Path, url: &str) -> String {
+        let Some(public_prefix) = &self.public_url_prefix else {
+            return url.to_string();
+        };
+        let route_prefix = self.with_url_form(self.url_prefix_for(path));
+        // Fixed paths aren't under the prefix, so they're public as they are.
+        let Some(rest) = url.strip_prefix(&route_prefix) else {
+            return url.to_string();
+        };
+
+        let public_prefix = public_prefix.trim_matches('/');
+        self.with_url_form(if public_prefix.is_empty() {
+            format!("/{rest}")
+        } else {
+            format!("/{public_prefix}/{rest}")
+        })
+    }
+
+    /// The URL prefix for `path`, with leading and trailing slashes.
+    fn url_prefix_for(&self, path: &Path) -> String {
+        let prefix = self
+            .url_prefixes
+            .iter()
+            .find(|(dir, _)| path_within(path, dir).is_some())
+            .map(|(_, prefix)| prefix.as_str())
+            .or(self.url_prefix.as_deref())
+            .unwrap_or("/static/");
+        slashed_prefix(prefix)
+    }
+
+    /// Mix `salt` into every file hash, so changing it busts all URLs at
+    /// once without touching any file contents.
+    pub fn salt(mut self, salt: impl Into<String>) -> Self {
+        self.salt = salt.into();
+        self
+    }
+
+    /// Read files `bytes` at a time while hashing them, eg. less to save
+    /// memory or more for throughput. Defaults to 64 KiB.
+    pub fn hash_buffer_size(mut self, bytes: usize) -> Self {
+        self.hash_buffer_size = Some(bytes);
+        self
+    }
+
+    /// Resolve directory paths like `/static/docs/` to that directory's
+    /// `index.html`, through `get` and `get_index`.
+    pub fn index_files(mut self, enabled: bool) -> Self {
+        self.index_files = enabled;
+        self
+    }
+
+    /// Set the digest used to fingerprint URLs. Defaults to MD5.
+    pub fn hash_algorithm(mut self, algorithm: HashAlgorithm) -> Self {
+        self.hash_algorithm = algorithm;
+        self
+    }
+
+    /// Fingerprint URLs with a custom digest instead of the
+    /// [`hash_algorithm`](Config::hash_algorithm). `new` is called to start
+    /// a fresh digest for every file.
+    pub fn hasher(mut self, new: fn() -> Box<dyn ContentHash>) -> Self {
+        self.hasher = Some(new);
+        self
+    }
+
+    /// Embed every file's bytes in the binary as a `content` field, and emit
+    /// `StaticFile::inline` returning them along with the MIME type.
+    pub fn embed(mut self, enabled: bool) -> Self {
+        self.embed = enabled;
+        self
+    }
+
+    /// Embed files through `include_bytes!` paths relative to the generated
+    /// file instead of absolute ones, so builds are reproducible across
+    /// checkouts as long as the assets keep their place relative to it.
+    /// Files with no relative path, eg. on another Windows drive, and
+    /// `Config::token_stream` output keep absolute paths.
+    pub fn relative_includes(mut self, enabled: bool) -> Self {
+        self.relative_includes = enabled;
+        self
+    }
+
+    /// Put embedded bytes, including precompressed variants, in top-level
+    /// `static BYTES_0: &[u8]` items that statics refer to, instead of
+    /// inline in each `StaticFile`. Large sets of assets compile faster this
+    /// way.
+    pub fn hoist_bytes(mut self, enabled: bool) -> Self {
+        self.hoist_bytes = enabled;
+        self
+    }
+
+    /// Emit a `gzip` field holding each file's gzip-compressed bytes, or
+    /// `None` where compression doesn't make it smaller.
+    #[cfg(feature = "gzip")]
+    pub fn gzip(mut self, enabled: bool) -> Self {
+        self.gzip = enabled;
+        self
+    }
+
+    /// Emit a `brotli` field holding each file's brotli-compressed bytes, or
+    /// `None` where compression doesn't make it smaller.
+    #[cfg(feature = "brotli")]
+    pub fn brotli(mut self, enabled: bool) -> Self {
+        self.brotli = enabled;
+        self
+    }
+
+    /// Whether [`Config::gzip`] and [`Config::brotli`] compress files with
+    /// `extension`, matched case-insensitively, overriding the built-in list
+    /// of already compressed formats like `png` and `woff2` that they skip.
+    /// Earlier calls take precedence.
+    #[cfg(any(feature = "gzip", feature = "brotli"))]
+    pub fn compress_extension(mut self, extension: impl Into<String>, enabled: bool) -> Self {
+        self.compressible_extensions
+            .push((extension.into(), enabled));
+        self
+    }
+
+    fn compressible(&self, extension: &str) -> bool {
+        self.compressible_extensions
+            .iter()
+            .find(|(ext, _)| ext.eq_ignore_ascii_case(extension))
+            .map_or_else(|| compressible(extension), |&(_, enabled)| enabled)
+    }
+
+    /// Embed every file brotli-compressed, replacing the `content` field with
+    /// a `content()` method that decompresses on first access and caches the
+    /// result. This trades a little CPU for a smaller binary, and requires
+    /// the crate including the generated file to depend on `brotli`.
+    #[cfg(feature = "brotli")]
+    pub fn compress_embedded(mut self, enabled: bool) -> Self {
+        self.compress_embedded = enabled;
+        self
+    }
+
+    /// Emit `StaticFile::response` and `IntoResponse` for `&'static
+    /// StaticFile`, serving embedded content with its content type and
+    /// `cache_control`. With [`Config::etag`], requests with a
+    /// matching `If-None-Match` get `304 Not Modified`. Needs
+    /// [`Config::embed`], and the crate including the generated file to
+    /// depend on `axum`.
+    #[cfg(feature = "axum")]
+    pub fn axum(mut self, enabled: bool) -> Self {
+        self.axum = enabled;
+        self
+    }
+
+    fn axum_enabled(&self) -> bool {
+        #[cfg(feature = "axum")]
+        return self.axum;
+        #[cfg(not(feature = "axum"))]
+        false
+    }
+
+    /// Emit a Rocket `Responder` for `&'static StaticFile`, serving it with
+    /// its content type and `cache_control`, from embedded content if
+    /// [`Config::embed`] is set or streamed from disk otherwise. With
+    /// [`Config::etag`], requests with a matching `If-None-Match` get `304 Not
+    /// Modified`. `StaticFile::rocket_route` serves every static at its URL,
+    /// and `StaticFile::fairing` mounts it. The crate including the generated
+    /// file needs to depend on `rocket`.
+    #[cfg(feature = "rocket")]
+    pub fn rocket(mut self, enabled: bool) -> Self {
+        self.rocket = enabled;
+        self
+    }
+
+    fn rocket_enabled(&self) -> bool {
+        #[cfg(feature = "rocket")]
+        return self.rocket;
+        #[cfg(not(feature = "rocket"))]
+        false
+    }
+
+    /// Emit `StaticFile::into_http_response`, building an `http::Response`
+    /// of embedded content with its content type, length and
+    /// `cache_control`, for `hyper` and other servers built on `http`, and
+    /// `StaticFile::not_found_http_response` for misses. With
+    /// [`Config::etag`], requests with a matching `If-None-Match` get `304
+    /// Not Modified`. Needs [`Config::embed`], and the crate including the
+    /// generated file to depend on `http` and `bytes`.
+    #[cfg(feature = "http")]
+    pub fn http(mut self, enabled: bool) -> Self {
+        self.http = enabled;
+        self
+    }
+
+    fn http_enabled(&self) -> bool {
+        #[cfg(feature = "http")]
+        return self.http;
+        #[cfg(not(feature = "http"))]
+        false
+    }
+
+    fn gzip_enabled(&self) -> bool {
+        #[cfg(feature = "gzip")]
+        return self.gzip;
+        #[cfg(not(feature = "gzip"))]
+        false
+    }
+
+    fn brotli_enabled(&self) -> bool {
+        #[cfg(feature = "brotli")]
+        return self.brotli;
+        #[cfg(not(feature = "brotli"))]
+        false
+    }
+
+    fn compress_embedded_enabled(&self) -> bool {
+        #[cfg(feature = "brotli")]
+        return self.compress_embedded;
+        #[cfg(not(feature = "brotli"))]
+        false
+    }
+
+    /// Emit an `etag` field holding a quoted hex digest of each file, using
+    /// `algorithm` independently of the URL hash.
+    pub fn etag(mut self, algorithm: HashAlgorithm) -> Self {
+        self.etag = Some(algorithm);
+        self
+    }
+
+    /// Write [`Config::etag`] digests in uppercase hex, independently of
+    /// the URL's [`HashEncoding`].
+    pub fn etag_uppercase(mut self, enabled: bool) -> Self {
+        self.etag_uppercase = enabled;
+        self
+    }
+
+    /// Emit an `alternates` field linking images like `hero.jpg` to the
+    /// `hero.avif` and `hero.webp` beside them, in that order, eg. for the
+    /// `<source>`s of a `<picture>`. The alternates are statics of their own
+    /// too.
+    pub fn alternates(mut self, enabled: bool) -> Self {
+        self.alternates = enabled;
+        self
+    }
+
+    /// Emit a `source_map` field linking files like `app.js` to the
+    /// `app.js.map` beside them. The map is a static of its own too.
+    pub fn source_maps(mut self, enabled: bool) -> Self {
+        self.source_maps = enabled;
+        self
+    }
+
+    /// Point the `sourceMappingURL` comment of files with a
+    /// [source map](Config::source_maps) at the map's hashed URL. Only
+    /// embedded content is rewritten, so this needs [`Config::embed`].
+    pub fn rewrite_source_map_urls(mut self, enabled: bool) -> Self {
+        self.rewrite_source_map_urls = enabled;
+        self
+    }
+
+    /// Generate `StaticFile::get` as a `match` on the name, which the compiler
+    /// can turn into a jump table, instead of a linear scan of `STATICS`.
+    pub fn match_lookup(mut self, enabled: bool) -> Self {
+        self.match_lookup = enabled;
+        self
+    }
+
+    /// Generate `StaticFile::get` as a lookup in a `HashMap`, built from
+    /// `STATICS` on the first call, instead of a linear scan. Nothing is
+    /// built until a lookup happens. Can't be combined with
+    /// [`Config::match_lookup`].
+    pub fn lazy_lookup(mut self, enabled: bool) -> Self {
+        self.lazy_lookup = enabled;
+        self
+    }
+
+    /// Generate `StaticFile::get` as a lookup in a [`phf`](https://docs.rs/phf)
+    /// perfect hash map, built at compile time, instead of a linear scan.
+    /// Can't be combined with [`Config::match_lookup`],
+    /// [`Config::lazy_lookup`] or [`Config::cfg`], and needs the crate
+    /// including the generated file to depend on `phf` 0.14.
+    #[cfg(feature = "phf")]
+    pub fn phf_lookup(mut self, enabled: bool) -> Self {
+        self.phf_lookup = enabled;
+        self
+    }
+
+    fn phf_lookup_enabled(&self) -> bool {
+        #[cfg(feature = "phf")]
+        return self.phf_lookup;
+        #[cfg(not(feature = "phf"))]
+        false
+    }
+
+    /// Generate `StaticFile::get_or_404`, which returns the asset at `path`,
+    /// eg. `static/404.html`, whenever a lookup misses.
+    pub fn fallback(mut self, path: impl Into<PathBuf>) -> Self {
+        self.fallback = Some(path.into());
+        self
+    }
+
+    /// Answer requests for unknown assets with `body` as `mime`, eg. a
+    /// branded HTML page, instead of a bare `404 Not Found`. The payload is
+    /// generated as `StaticFile::NOT_FOUND_MIME` and
+    /// `StaticFile::NOT_FOUND_BODY`, which `Config::axum`'s
+    /// `StaticFile::serve`, `Config::rocket`'s fairing and `Config::http`'s
+    /// `StaticFile::not_found_http_response` respond with.
+    pub fn not_found_body(mut self, mime: impl Into<String>, body: impl Into<Vec<u8>>) -> Self {
+        self.not_found = Some((mime.into(), body.into()));
+        self
+    }
+
+    /// Emit a `StaticKind` enum classifying files by MIME type, along with
+    /// `StaticFile::kind`, `StaticFile::all` and `StaticFile::by_kind` for
+    /// eg. preloading every stylesheet.
+    pub fn kinds(mut self, enabled: bool) -> Self {
+        self.kinds = enabled;
+        self
+    }
+
+    /// Emit a `pub const` route for every static, eg. `MAIN_CSS_ROUTE`, and
+    /// `StaticFile::routes` listing `(method, path, file)` for registering
+    /// them with a router.
+    pub fn routes(mut self, enabled: bool) -> Self {
+        self.routes = enabled;
+        self
+    }
+
+    /// Generate a `static_url!` macro, eg. `static_url!("vendor/script.js")`,
+    /// which expands to the hashed URL of the file at that path relative to
+    /// its asset directory, and fails to compile for unknown paths. It's
+    /// exported with `pub(crate) use`, so other modules can use it through
+    /// the module the file is included in.
+    pub fn url_macro(mut self, enabled: bool) -> Self {
+        self.url_macro = enabled;
+        self
+    }
+
+    /// Emit a `rel_path` field holding each file's path relative to its
+    /// asset directory, and `StaticFile::read` for reading it at runtime.
+    /// Files are read from `file_name` unless `StaticFile::set_serve_root`
+    /// moves them under another directory, eg. a fixture in tests.
+    pub fn serve_root(mut self, enabled: bool) -> Self {
+        self.serve_root = enabled;
+        self
+    }
+
+    /// Generate `StaticFile::resolve`, which maps a file's path relative to
+    /// its asset directory, eg. `vendor/script.js`, to its hashed URL, like
+    /// the keys of the [manifest](Config::manifest).
+    pub fn resolve(mut self, enabled: bool) -> Self {
+        self.resolve = enabled;
+        self
+    }
+
+    /// Emit a `hash` field holding each file's hash as it appears in URLs,
+    /// and `StaticFile::find_by_hash` for looking files up by it, eg. for
+    /// integrity checks. Files served [without a hash](Config::no_hash)
+    /// still have one.
+    pub fn content_hashes(mut self, enabled: bool) -> Self {
+        self.content_hashes = enabled;
+        self
+    }
+
+    /// Emit `StaticFile::url`, which prefixes `name` with a base URL chosen at
+    /// runtime, eg. a CDN host per environment. The base is whatever was
+    /// passed to `StaticFile::set_base_url`, or else the value of the `var`
+    /// environment variable, read once on first use.
+    pub fn base_url_env(mut self, var: impl Into<String>) -> Self {
+        self.base_url_env = Some(var.into());
+        self
+    }
+
+    /// Put every static at the top level instead of in modules matching its
+    /// directories, with the directories prefixed to its identifier, eg.
+    /// `vendor_script_js` for `vendor/script.js`. URLs stay nested.
+    pub fn flat(mut self, enabled: bool) -> Self {
+        self.flat = enabled;
+        self
+    }
+
+    /// Generate the asset directories as layers of one tree rather than side
+    /// by side, eg. a base set of assets followed by a theme. A file in a
+    /// later directory replaces the one at the same relative path in an
+    /// earlier one, and directories present in several are merged into one
+    /// module.
+    pub fn overlay(mut self, enabled: bool) -> Self {
+        self.overlay = enabled;
+        self
+    }
+
+    /// Generate the extra file at `file_path` as if it were at `logical_path`
+    /// in an asset directory, eg. `libs/foo.js` for `libs::foo_js` and
+    /// `/static/libs/foo-1a2b3c.js`, rather than at the top level under its
+    /// own file name. `logical_path` must be relative, and can't be in a
+    /// directory that one of the asset directories also has.
+    pub fn logical_path(
+        mut self,
+        file_path: impl Into<PathBuf>,
+        logical_path: impl Into<PathBuf>,
+    ) -> Self {
+        self.logical_paths
+            .push((file_path.into(), logical_path.into()));
+        self
+    }
+
+    /// Set the `cache_control` field of files matching the glob `pattern` to
+    /// `value`, eg. `.cache_control("*.json", "no-cache")`. Earlier patterns
+    /// take precedence. Unmatched HTML and [unhashed](Config::no_hash) files
+    /// default to `no-cache`, as their URLs are usually linked to directly,
+    /// and everything else to a year long `immutable`.
+    pub fn cache_control(mut self, pattern: impl Into<String>, value: impl Into<String>) -> Self {
+        self.cache_controls.push((pattern.into(), value.into()));
+        self
+    }
+
+    /// The `Cache-Control` value for the file at `rel_path`, served with
+    /// `extension`.
+    fn cache_control_for(&self, rel_path: &str, extension: &str) -> &str {
+        if let Some((_, value)) = self
+            .cache_controls
+            .iter()
+            .find(|(pattern, _)| glob::matches(pattern, rel_path))
+        {
+            return value;
+        }
+
+        if extension.eq_ignore_ascii_case("html")
+            || extension.eq_ignore_ascii_case("htm")
+            || self.is_unhashed(rel_path)
+        {
+            "no-cache"
+        } else {
+            IMMUTABLE
+        }
+    }
+
+    /// Serve files matching the glob `pattern` at their plain path without a
+    /// hash, eg. `.no_hash("favicon.ico")` for `/static/favicon.ico`, for
+    /// URLs that other systems reference directly.
+    pub fn no_hash(mut self, pattern: impl Into<String>) -> Self {
+        self.unhashed.push(pattern.into());
+        self
+    }
+
+    /// Serve files matching the glob `pattern` as downloads, eg.
+    /// `.download("*.pdf")`. Every static then has a `disposition` field,
+    /// `Some("attachment; filename=\"brochure.pdf\"")` for these and `None`
+    /// for the rest, which the Axum and Rocket responses send as
+    /// `Content-Disposition`.
+    pub fn download(mut self, pattern: impl Into<String>) -> Self {
+        self.downloads.push(pattern.into());
+        self
+    }
+
+    /// Mark files matching the glob `pattern` for preloading, eg.
+    /// `.preload("fonts/*.woff2")` for files on the critical path. Every
+    /// static then has a `preload` field, and `StaticFile::preload_link`
+    /// returns a `<link rel="preload">` tag for the marked ones.
+    pub fn preload(mut self, pattern: impl Into<String>) -> Self {
+        self.preloads.push(pattern.into());
+        self
+    }
+
+    /// Whether the file at `rel_path` is served without a hash.
+    fn is_unhashed(&self, rel_path: &str) -> bool {
+        self.unhashed
+            .iter()
+            .chain(&self.fixed_paths)
+            .any(|pattern| glob::matches(pattern, rel_path))
+    }
+
+    /// Serve files matching the glob `pattern` at exactly their path from
+    /// the site root, without the URL prefix or a hash, eg.
+    /// `.fixed_path("robots.txt")` for `/robots.txt` or
+    /// `.fixed_path(".well-known/*")` for `/.well-known/security.txt`. Like
+    /// [unhashed](Config::no_hash) files, they default to `no-cache`. Dot
+    /// directories like `.well-known` also need [`Config::include_hidden`].
+    pub fn fixed_path(mut self, pattern: impl Into<String>) -> Self {
+        self.fixed_paths.push(pattern.into());
+        self
+    }
+
+    fn is_fixed_path(&self, rel_path: &str) -> bool {
+        self.fixed_paths
+            .iter()
+            .any(|pattern| glob::matches(pattern, rel_path))
+    }
+
+    /// Re-export every static in a directory module at the top level, eg.
+    /// `script_js` for `vendor::script_js`, failing if two statics would
+    /// share a name there.
+    pub fn reexport(mut self, enabled: bool) -> Self {
+        self.reexport = enabled;
+        self
+    }
+
+    /// Emit `images`, `scripts` and `styles` modules re-exporting every static
+    /// of that kind by MIME type, eg. `images::logo_png` wherever `logo.png`
+    /// is. Statics sharing a name across directories get a numbered suffix in
+    /// order, eg. `logo_png_2`. Fails if a top-level directory module would
+    /// have the same name.
+    pub fn categories(mut self, enabled: bool) -> Self {
+        self.categories = enabled;
+        self
+    }
+
+    /// Emit a `pub const DIR_HASH: &str` in every directory module, hashing
+    /// the paths and hashes of all files under it, eg. as the cache key of a
+    /// documentation site served as a whole. It's encoded and truncated like
+    /// URL hashes.
+    pub fn dir_hashes(mut self, enabled: bool) -> Self {
+        self.dir_hashes = enabled;
+        self
+    }
+
+    /// Include files and directories in asset directories whose names start
+    /// with `.`, eg. `.well-known`. They're skipped by default so things like
+    /// `.git` and `.DS_Store` don't become statics. Extra files are always
+    /// included.
+    pub fn include_hidden(mut self, enabled: bool) -> Self {
+        self.include_hidden = enabled;
+        self
+    }
+
+    /// Skip files in asset directories last modified before `time`, eg. stale
+    /// leftovers of an earlier build. Extra files are always included.
+    pub fn modified_after(mut self, time: SystemTime) -> Self {
+        self.modified_after = Some(time);
+        self
+    }
+
+    /// Skip files in asset directories last modified at or after `time`.
+    /// Extra files are always included.
+    pub fn modified_before(mut self, time: SystemTime) -> Self {
+        self.modified_before = Some(time);
+        self
+    }
+
+    /// Whether the file at `path` is within the [`Config::modified_after`]
+    /// and [`Config::modified_before`] range.
+    fn modified_in_range(&self, path: &Path) -> std::io::Result<bool> {
+        if self.modified_after.is_none() && self.modified_before.is_none() {
+            return Ok(true);
+        }
+        let modified = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .map_err(|err| with_path(err, path))?;
+        Ok(self.modified_after.is_none_or(|after| modified >= after)
+            && self.modified_before.is_none_or(|before| modified < before))
+    }
+
+    /// Set how identifiers are derived from file names. Results are
+    /// sanitized into valid identifiers, and files whose identifiers would
+    /// collide within a module fall back to [`IdentStrategy::FileName`].
+    pub fn ident_strategy(mut self, strategy: IdentStrategy) -> Self {
+        self.ident_strategy = strategy;
+        self
+    }
+
+    /// For each of `paths`, files in the same directory, the identifiers of
+    /// its [alternates](Config::alternates).
+    fn image_alternates(&self, paths: &[PathBuf], idents: &[String]) -> Vec<Vec<String>> {
+        const ALTERNATES: [&str; 2] = ["avif", "webp"];
+
+        let extension = |path: &Path| {
+            path.extension()
+                .and_then(|extension| extension.to_str())
+                .unwrap_or_default()
+                .to_ascii_lowercase()
+        };
+
+        paths
+            .iter()
+            .map(|path| {
+                let is_image = mime_type_from_extension(&extension(path))
+                    .is_some_and(|mime| mime.starts_with("image/"));
+                if !is_image || ALTERNATES.contains(&extension(path).as_str()) {
+                    return Vec::new();
+                }
+
+                ALTERNATES
+                    .iter()
+                    .filter_map(|alternate| {
+                        paths.iter().position(|other| {
+                            other.file_stem() == path.file_stem()
+                                && extension(other) == *alternate
+                                && self.cfg_attribute(other) == self.cfg_attribute(path)
+                        })
+                    })
+                    .map(|i| idents[i].clone())
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// The identifiers for files in the same module, in the same order.
+    fn file_idents(&self, paths: &[PathBuf]) -> std::io::Result<Vec<String>> {
+        let mut idents = paths
+            .iter()
+            .map(|path| file_ident(path, self.ident_strategy))
+            .collect::<std::io::Result<Vec<_>>>()?;
+
+        let ambiguous: Vec<bool> = idents
+            .iter()
+            .map(|ident| idents.iter().filter(|other| *other == ident).count() > 1)
+            .collect();
+        for (i, path) in paths.iter().enumerate() {
+            if ambiguous[i] {
+                idents[i] = file_ident(path, IdentStrategy::FileName)?;
+            }
+        }
+
+        for (i, ident) in idents.iter().enumerate() {
+            if let Some(j) = idents[..i].iter().position(|other| other == ident) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "{} and {} would both be named `{}`",
+                        paths[j].display(),
+                        paths[i].display(),
+                        ident
+                    ),
+                ));
+            }
+        }
+
+        Ok(idents)
+    }
+
+    /// Generate only `fields` of the always present `StaticFile` fields, eg.
+    /// none for statics with just a `name` and `mime`, to keep the type and
+    /// binary lean. Fails if another option needs one that's left out.
+    pub fn fields(mut self, fields: impl IntoIterator<Item = Field>) -> Self {
+        self.fields = Some(fields.into_iter().collect());
+        self
+    }
+
+    /// Derive `derives` on `StaticFile` as well as `Debug`. Fails if the
+    /// fields generated can't support one of them.
+    pub fn derives(mut self, derives: impl IntoIterator<Item = Derive>) -> Self {
+        self.derives = derives.into_iter().collect();
+        self
+    }
+
+    /// Fail if options conflict with each other, before generating anything.
+    fn check_options(&self) -> std::io::Result<()> {
+        if self.no_std {
+            if let Some((option, _)) = self
+                .options_needing_std()
+                .iter()
+                .find(|(_, enabled)| *enabled)
+            {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("{option} needs std, which Config::no_std rules out"),
+                ));
+            }
+        }
+
+        if self.lazy_lookup && self.match_lookup {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Config::lazy_lookup and Config::match_lookup are alternatives",
+            ));
+        }
+        if self.phf_lookup_enabled() {
+            if self.lazy_lookup || self.match_lookup {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "Config::phf_lookup, Config::lazy_lookup and Config::match_lookup are alternatives",
+                ));
+            }
+            // The map is built here, so it can't leave out files whose cfg
+            // doesn't hold.
+            if !self.cfgs.is_empty() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "Config::phf_lookup can't be combined with Config::cfg",
+                ));
+            }
+        }
+
+        if self.axum_enabled() && !self.embed && !self.compress_embedded_enabled() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "axum responses need embedded content, see Config::embed",
+            ));
+        }
+        let embedded = self.embed || self.compress_embedded_enabled();
+        let field_users = [
+            (Field::FileName, "Config::serve_root", self.serve_root),
+            (
+                Field::FileName,
+                "Config::rocket without embedded content",
+                self.rocket_enabled() && !embedded,
+            ),
+            (Field::Meta, "Config::meta", !self.meta.is_empty()),
+            (Field::CacheControl, "Config::axum", self.axum_enabled()),
+            (Field::CacheControl, "Config::rocket", self.rocket_enabled()),
+            (Field::CacheControl, "Config::http", self.http_enabled()),
+        ];
+        if let Some((field, option, _)) = field_users
+            .iter()
+            .find(|(field, _, enabled)| *enabled && !self.has_field(*field))
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{option} needs the Field::{field:?} that Config::fields leaves out"),
+            ));
+        }
+        if self.derives.contains(&Derive::Copy) {
+            if !self.derives.contains(&Derive::Clone) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "Derive::Copy needs Derive::Clone",
+                ));
+            }
+            if self.compress_embedded_enabled() {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "Derive::Copy can't be derived over Config::compress_embedded's content cache",
+                ));
+            }
+        }
+        if self.http_enabled() && !self.embed && !self.compress_embedded_enabled() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "http responses need embedded content, see Config::embed",
+            ));
+        }
+        if self.rewrite_source_map_urls && !self.embed {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "rewriting source map URLs needs embedded content, see Config::embed",
+            ));
+        }
+
+        // `get_or_404` refers to the fallback unconditionally, so it can't be
+        // compiled out.
+        if let Some(path) = self
+            .fallback
+            .as_ref()
+            .filter(|path| self.cfg_attribute(path).is_some())
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "fallback {} is behind a Config::cfg predicate",
+                    path.display()
+                ),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// The derives on `StaticFile`, each once and in [`Derive`]'s order.
+    fn derive_names(&self) -> Vec<String> {
+        [Derive::Clone, Derive::Copy, Derive::Default]
+            .into_iter()
+            .filter(|derive| self.derives.contains(derive))
+            .map(|derive| format!("{derive:?}"))
+            .collect()
+    }
+
+    fn has_field(&self, field: Field) -> bool {
+        self.fields
+            .as_ref()
+            .is_none_or(|fields| fields.contains(&field))
+    }
+
+    /// Set the path generated modules use to import `StaticFile`, eg.
+    /// `crate::assets::StaticFile` when the file is included in
+    /// `crate::assets`. Defaults to `super::StaticFile`.
+    pub fn static_file_path(mut self, path: impl Into<String>) -> Self {
+        self.static_file_path = Some(path.into());
+        self
+    }
+
+    /// Set how file hashes are encoded into URLs.
+    pub fn hash_encoding(mut self, encoding: HashEncoding) -> Self {
+        self.hash_encoding = encoding;
+        self
+    }
+
+    /// Set whether URLs are absolute paths or relative to the current
+    /// document. `StaticFile::get` matches requests either way.
+    pub fn url_form(mut self, form: UrlForm) -> Self {
+        self.url_form = form;
+        self
+    }
+
+    /// `url`, an absolute path, in the configured [`UrlForm`].
+    fn with_url_form(&self, url: String) -> String {
+        match self.url_form {
+            UrlForm::Absolute => url,
+            UrlForm::Relative => url.trim_start_matches('/').to_string(),
+            UrlForm::DotRelative => format!(".{url}"),
+        }
+    }
+
+    /// Set where the hash goes in each file's URL.
+    pub fn hash_placement(mut self, placement: HashPlacement) -> Self {
+        self.hash_placement = placement;
+        self
+    }
+
+    /// Serve files with the extension `from` as though they had the
+    /// extension `to`, eg. `.ts` sources compiled to `.js`, so their URL and
+    /// MIME type use `to` while `file_name` still names the source. Matched
+    /// case-insensitively.
+    pub fn serve_extension(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.served_extensions.push((from.into(), to.into()));
+        self
+    }
+
+    /// The extension files with `extension` are served with.
+    fn served_extension<'a>(&'a self, extension: &'a str) -> &'a str {
+        self.served_extensions
+            .iter()
+            .find(|(from, _)| from.eq_ignore_ascii_case(extension))
+            .map_or(extension, |(_, to)| to)
+    }
+
+    /// Hash text files as if their CRLF line endings were LF, so a file's URL
+    /// and ETag don't depend on whether it was checked out on Windows. Files
+    /// are text by the MIME type of their extension: `text/*`, JavaScript,
+    /// JSON, XML and types like SVG built on those. The served and embedded
+    /// content is unchanged.
+    pub fn normalize_line_endings(mut self, enabled: bool) -> Self {
+        self.normalize_line_endings = enabled;
+        self
+    }
+
+    /// Whether the file at `path` is hashed with normalized line endings.
+    fn normalizes_line_endings(&self, path: &Path) -> bool {
+        if !self.normalize_line_endings {
+            return false;
+        }
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default();
+        let extension = self.served_extension(extension);
+        let mime = self
+            .extension_mimes
+            .iter()
+            .find(|(ext, _)| ext.eq_ignore_ascii_case(extension))
+            .map(|(_, mime)| mime.as_str())
+            .or_else(|| mime_type_from_extension(extension));
+        mime.is_some_and(is_text_mime)
+    }
+
+    /// Report files larger than `bytes` in [`Report::large_files`], to catch
+    /// accidentally bundled assets. When run from a build script, each also
+    /// gets a `cargo:warning`. This never fails the build.
+    pub fn warn_size(mut self, bytes: u64) -> Self {
+        self.warn_size = Some(bytes);
+        self
+    }
+
+    /// Truncate encoded file hashes to at most `length` characters.
+    pub fn hash_length(mut self, length: usize) -> Self {
+        self.hash_length = Some(length);
+        self
+    }
+
+    /// Write `header` as comment lines at the top of the generated file, eg.
+    /// a license notice, after the `// @generated by cacheb` marker that
+    /// tells tools to skip the file. Without one the file starts with just
+    /// `// @generated by cacheb — do not edit`.
+    pub fn header(mut self, header: impl Into<String>) -> Self {
+        self.header = Some(header.into());
+        self
+    }
+
+    /// Emit a `GENERATED_AT` constant holding the RFC 3339 time codegen ran.
+    /// Off by default, as it makes the output differ on every build.
+    pub fn generated_at(mut self, enabled: bool) -> Self {
+        self.generated_at = enabled;
+        self
+    }
+
+    /// Attach the `key`/`value` pair to the `meta` field of every file
+    /// matching `glob`, eg. `.meta("fonts/**", "preload", "true")`.
+    pub fn meta(
+        mut self,
+        glob: impl Into<String>,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.meta.push((glob.into(), key.into(), value.into()));
+        self
+    }
+
+    /// Fail code generation when two files' paths differ only by case, eg.
+    /// `Logo.png` and `logo.png`, instead of listing them in
+    /// [`Report::case_collisions`] with a `cargo:warning`.
+    pub fn deny_case_collisions(mut self, enabled: bool) -> Self {
+        self.deny_case_collisions = enabled;
+        self
+    }
+
+    /// Set the doc comment on every static, replacing `{path}`, `{url}`,
+    /// `{hash}` and `{mime}` with the file's path, hashed URL, hash and MIME
+    /// type, eg. `"{url} ({mime})"`. Each line becomes a line of the
+    /// comment. Defaults to `From "{path}"`.
+    pub fn doc_template(mut self, template: impl Into<String>) -> Self {
+        self.doc_template = Some(template.into());
+        self
+    }
+
+    /// Also emit an upper-case `pub const` reference to every static, eg.
+    /// `MAIN_CSS` for `main_css`, except for statics already in upper case.
+    /// The statics themselves can already be referenced from consts since
+    /// Rust 1.83.
+    pub fn const_refs(mut self, enabled: bool) -> Self {
+        self.const_refs = enabled;
+        self
+    }
+
+    /// Emit a `pub enum Asset` with a variant for every static, named after
+    /// its module path and identifier, eg. `VendorScriptJs` for
+    /// `vendor::script_js`, and `Asset::file` mapping each to its static. A
+    /// `match` on it must then handle every asset. Variants that would share
+    /// a name get a numbered suffix in order, eg. `AppJs2`.
+    pub fn asset_enum(mut self, enabled: bool) -> Self {
+        self.asset_enum = enabled;
+        self
+    }
+
+    /// Generate a file usable in `#![no_std]` crates, failing if an option
+    /// that needs `std` is set: [`Config::base_url_env`],
+    /// [`Config::serve_root`], [`Config::lazy_lookup`],
+    /// [`Config::preload`], `Config::compress_embedded`, `Config::axum`,
+    /// `Config::rocket` or `Config::http`.
+    pub fn no_std(mut self, enabled: bool) -> Self {
+        self.no_std = enabled;
+        self
+    }
+
+    /// Every option [`Config::no_std`] rules out, and whether it's set. The
+    /// `no_std` docs list each of them.
+    fn options_needing_std(&self) -> [(&'static str, bool); 8] {
+        [
+            ("Config::base_url_env", self.base_url_env.is_some()),
+            ("Config::serve_root", self.serve_root),
+            ("Config::lazy_lookup", self.lazy_lookup),
+            ("Config::preload", !self.preloads.is_empty()),
+            (
+                "Config::compress_embedded",
+                self.compress_embedded_enabled(),
+            ),
+            ("Config::axum", self.axum_enabled()),
+            ("Config::rocket", self.rocket_enabled()),
+            ("Config::http", self.http_enabled()),
+        ]
+    }
+
+    /// Drop the leading `prefix` directory from paths relative to an asset
+    /// directory, so with `dist` the file `dist/app/main.js` is served at
+    /// `/static/app/main.js` and lives in module `app`.
+    pub fn strip_prefix(mut self, prefix: impl Into<PathBuf>) -> Self {
+        self.strip_prefix = Some(prefix.into());
+        self
+    }
+
+    /// `digest` encoded for a URL, truncated to the hash length.
+    fn encode_hash(&self, digest: &[u8]) -> String {
+        let mut hash = self.hash_encoding.encode(digest);
+        if let Some(length) = self.hash_length {
+            hash.truncate(length);
+        }
+        hash
+    }
+
+    /// How ETags are encoded.
+    fn etag_encoding(&self) -> HashEncoding {
+        if self.etag_uppercase {
+            HashEncoding::UpperHex
+        } else {
+            HashEncoding::Hex
+        }
+    }
+
+    /// `path` relative to `base_dir`, without any configured prefix.
+    fn relative_path<'p>(&self, path: &'p Path, base_dir: &Path) -> Cow<'p, Path> {
+        let rel_path = match self.logical_path_of(path) {
+            Some(logical_path) => Cow::Owned(logical_path.to_path_buf()),
+            None => path_within(path, base_dir).unwrap_or(Cow::Borrowed(path)),
+        };
+        let stripped = self
+            .strip_prefix
+            .as_ref()
+            .and_then(|prefix| rel_path.strip_prefix(prefix).ok())
+            .map(Path::to_path_buf);
+        match stripped {
+            Some(stripped) => Cow::Owned(stripped),
+            None => rel_path,
+        }
+    }
+
+    /// The [logical path](Config::logical_path) of the extra file at `path`,
+    /// which is [portable](portable_path), if it has one.
+    fn logical_path_of(&self, path: &Path) -> Option<&Path> {
+        self.logical_paths
+            .iter()
+            .find(|(file_path, _)| *portable_path(file_path) == *path)
+            .map(|(_, logical_path)| logical_path.as_path())
+    }
+
+    /// The `#[cfg]` attribute to put on items generated for `path`, if any.
+    fn cfg_attribute(&self, path: &Path) -> Option<String> {
+        let predicates: Vec<&str> = self
+            .cfgs
+            .iter()
+            .filter(|(cfg_path, _)| path_within(path, cfg_path).is_some())
+            .map(|(_, predicate)| predicate.as_str())
+            .collect();
+
+        match predicates.as_slice() {
+            [] => None,
+            [predicate] => Some(format!("#[cfg({predicate})]")),
+            predicates => Some(format!("#[cfg(all({}))]", predicates.join(", "))),
+        }
+    }
+
+    /// Generate the statics file at `out_path` using these options, or write
+    /// it to stdout if `out_path` is `-`. An `out_path` inside one of the
+    /// asset directories is skipped rather than becoming an asset itself, as
+    /// are asset directories inside another, whose files are generated once
+    /// as part of the outer one.
+    pub fn codegen(
+        &self,
+        out_path: &Path,
+        asset_dirs: &[PathBuf],
+        extra_files: &[PathBuf],
+    ) -> std::io::Result<Report> {
+        if out_path == Path::new("-") {
+            let mut stdout = std::io::stdout().lock();
+            let report = self.codegen_to_writer(&mut stdout, asset_dirs, extra_files)?;
+            stdout.flush()?;
+            return Ok(report);
+        }
+
+        let (code, output) = generate(self, asset_dirs, extra_files, Some(out_path))?;
+        self.write_generated(out_path, &code, output)
+    }
+
+    /// Write the generated `code` to `out_path`, warning about anything the
+    /// report in `output` found when run from a build script.
+    fn write_generated(
+        &self,
+        out_path: &Path,
+        code: &str,
+        output: Generated,
+    ) -> std::io::Result<Report> {
+        write_if_changed(out_path, code.as_bytes())?;
+
+        // Cargo sets `OUT_DIR` for build scripts, and only they can warn.
+        if std::env::var_os("OUT_DIR").is_some() {
+            for (path, size) in &output.report.large_files {
+                println!(
+                    "cargo:warning={} is {} bytes, over the warn_size of {}",
+                    path.display(),
+                    size,
+                    self.warn_size.unwrap_or_default()
+                );
+            }
+            for path in &output.report.unportable_names {
+                println!(
+                    "cargo:warning={} ends in a dot or space, which Windows strips from file names",
+                    path.display()
+                );
+            }
+            for (path, other) in &output.report.case_collisions {
+                println!(
+                    "cargo:warning={} and {} differ only by case, so collide on case-insensitive filesystems",
+                    path.display(),
+                    other.display()
+                );
+            }
+        }
+
+        Ok(output.report)
+    }
+
+    /// [Generate](Config::codegen) the statics file at `out_path` again after
+    /// the run that returned `previous`, with the files whose hashes changed
+    /// since then in [`Report::changes`]. Keep each run's report for the
+    /// next, eg. in a dev server's rebuild loop.
+    pub fn codegen_since(
+        &self,
+        out_path: &Path,
+        asset_dirs: &[PathBuf],
+        extra_files: &[PathBuf],
+        previous: &Report,
+    ) -> std::io::Result<Report> {
+        let mut report = self.codegen(out_path, asset_dirs, extra_files)?;
+        report.changes = Some(previous.hashes.diff(&report.hashes));
+        Ok(report)
+    }
+
+    /// Generate the statics file into `writer` instead of a file, eg. for
+    /// piping into another tool. [`Config::codegen`] does this for stdout
+    /// when `out_path` is `-`. Code is written as each file is generated
+    /// rather than held until the end, so if generating fails, `writer` may
+    /// have been given part of it.
+    pub fn codegen_to_writer(
+        &self,
+        writer: &mut impl Write,
+        asset_dirs: &[PathBuf],
+        extra_files: &[PathBuf],
+    ) -> std::io::Result<Report> {
+        let generator = Generator::new(self, None, None)?;
+        let output = generate_to(writer, generator, asset_dirs, extra_files)?;
+        Ok(output.report)
+    }
+
+    /// The JSON manifest mapping each file's path, relative to its asset
+    /// directory, to its hashed URL, eg. `{"app/main.js":
+    /// "/static/app/main-1a2b3c.js"}`, without writing the Rust file. Keys are
+    /// sorted, and URLs match what [`Config::codegen`] generates. No code is
+    /// generated for it, and files are only precompressed for
+    /// [`Config::manifest_sizes`].
+    pub fn manifest(
+        &self,
+        asset_dirs: &[PathBuf],
+        extra_files: &[PathBuf],
+    ) -> std::io::Result<String> {
+        let manifest = generate_manifest(self, asset_dirs, extra_files, None)?;
+        Ok(manifest.to_json())
+    }
+
+    /// Record each file's size in bytes in the [manifest](Config::manifest),
+    /// as served and as each precompressed variant, eg. `{"app.js": {"url":
+    /// "/static/app-1a2b3c.js", "sizes": {"br": 312, "gzip": 340,
+    /// "identity": 1024}}}`. Variants aren't listed when they weren't
+    /// smaller than the file, as they're never served.
+    pub fn manifest_sizes(mut self, enabled: bool) -> Self {
+        self.manifest_sizes = enabled;
+        self
+    }
+
+    /// Write the [manifest](Config::manifest) to `manifest_path`, leaving it
+    /// untouched if unchanged.
+    pub fn write_manifest(
+        &self,
+        manifest_path: &Path,
+        asset_dirs: &[PathBuf],
+        extra_files: &[PathBuf],
+    ) -> std::io::Result<()> {
+        let manifest = generate_manifest(self, asset_dirs, extra_files, Some(manifest_path))?;
+        let manifest = manifest.to_json();
+        write_if_changed(manifest_path, manifest.as_bytes())?;
+        Ok(())
+    }
+
+    /// Update the statics file at `out_path` after the contents of the single
+    /// file `changed` changed, eg. from a dev server's file watcher. Only that
+    /// file is rehashed and its URL and ETag patched into the existing file,
+    /// so the order of the statics and `STATICS` stay as they were.
+    ///
+    /// Falls back to a full [`Config::codegen`] when patching can't work:
+    /// `changed` is new, removed or not in the file yet, or other generated
+    /// content depends on its bytes, as with precompressed variants,
+    /// [directory hashes](Config::dir_hashes) or
+    /// [rewritten source map URLs](Config::rewrite_source_map_urls).
+    pub fn codegen_changed(
+        &self,
+        out_path: &Path,
+        asset_dirs: &[PathBuf],
+        extra_files: &[PathBuf],
+        changed: &Path,
+    ) -> std::io::Result<()> {
+        let patched = match fs::read_to_string(out_path) {
+            Ok(code) => self
+                .changed_file(asset_dirs, extra_files, changed)?
+                .and_then(|changed| changed.patch_code(&code, self.url_form)),
+            Err(_) => None,
+        };
+
+        match patched {
+            Some(code) => write_if_changed(out_path, code.as_bytes()).map(drop),
+            None => self.codegen(out_path, asset_dirs, extra_files).map(drop),
+        }
+    }
+
+    /// Update the [manifest](Config::manifest) at `manifest_path` after the
+    /// contents of `changed` changed, like [`Config::codegen_changed`] does
+    /// for the statics file.
+    pub fn write_manifest_changed(
+        &self,
+        manifest_path: &Path,
+        asset_dirs: &[PathBuf],
+        extra_files: &[PathBuf],
+        changed: &Path,
+    ) -> std::io::Result<()> {
+        let existing = fs::read_to_string(manifest_path)
+            .ok()
+            .and_then(|json| Manifest::from_json(&json).ok());
+
+        if let (Some(mut manifest), Some(changed_file)) = (
+            existing,
+            self.changed_file(asset_dirs, extra_files, changed)?,
+        ) {
+            if manifest.get(&changed_file.rel_path).is_some() {
+                let rel_path = changed_file.rel_path;
+                manifest.insert(rel_path.clone(), changed_file.url);
+                if self.manifest_sizes {
+                    let size = fs::metadata(changed)?.len();
+                    manifest.insert_size(&rel_path, "identity", size);
+                }
+                write_if_changed(manifest_path, manifest.to_json().as_bytes())?;
+                return Ok(());
+            }
+        }
+
+        self.write_manifest(manifest_path, asset_dirs, extra_files)
+    }
+
+    /// The new URL and ETag of `changed`, unless it no longer exists or
+    /// other generated content depends on its bytes.
+    fn changed_file(
+        &self,
+        asset_dirs: &[PathBuf],
+        extra_files: &[PathBuf],
+        changed: &Path,
+    ) -> std::io::Result<Option<ChangedFile>> {
+        if self.rewrite_source_map_urls
+            || self.doc_template.is_some()
+            || self.public_url_prefix.is_some()
+            || self.content_hashes
+            || self.dir_hashes
+            || self.gzip_enabled()
+            || self.brotli_enabled()
+            || self.compress_embedded_enabled()
+            || self.phf_lookup_enabled()
+            || self.modified_after.is_some()
+            || self.modified_before.is_some()
+            || self.sniff_mime
+            || !self.has_field(Field::FileName)
+            || !self.dependencies.is_empty()
+            || self.overlay
+        {
+            return Ok(None);
+        }
+        let Ok(full_path) = fs::canonicalize(changed) else {
+            return Ok(None);
+        };
+
+        // The path and base directory the file was generated with, as
+        // `generate` walks them.
+        let in_asset_dir = distinct_asset_dirs(asset_dirs)
+            .into_iter()
+            .find_map(|asset_dir| {
+                let rel_path = full_path
+                    .strip_prefix(fs::canonicalize(asset_dir).ok()?)
+                    .ok()?;
+                Some((asset_dir.join(rel_path), asset_dir.clone()))
+            });
+        let in_extra_files = || {
+            extra_files
+                .iter()
+                .find(|file_path| fs::canonicalize(file_path).is_ok_and(|path| path == full_path))
+                .map(|file_path| {
+                    let parent = file_path.parent().unwrap_or(Path::new(""));
+                    (file_path.clone(), parent.to_path_buf())
+                })
+        };
+        let Some((path, base_dir)) = in_asset_dir.or_else(in_extra_files) else {
+            return Ok(None);
+        };
+
+        let generator = Generator::new(self, None, None)?;
+        let digests = generator.digests(&path, None)?;
+        let url = generator.url_path(&path, &base_dir, &digests[0])?;
+        let etag = self
+            .etag
+            .map(|_| format!("\"{}\"", self.etag_encoding().encode(&digests[1])));
+
+        let portable = portable_path(&path);
+        let rel_path = self.relative_path(&portable, &base_dir);
+        let rel_path = path_to_str(rel_path.as_os_str(), &path)?.replace('\\', "/");
+
+        Ok(Some(ChangedFile {
+            file_name: path_to_str(full_path.as_os_str(), &path)?.to_string(),
+            rel_path,
+            url,
+            etag,
+        }))
+    }
+}
 
 pub fn codegen(
     out_path: &Path,
     asset_dirs: &[PathBuf],
     extra_files: &[PathBuf],
-) -> std::io::Result<()> {
-    let mut output = String::new();
-    let mut static_files = Vec::new();
-    let mut module_map: HashMap<String, Vec<String>> = HashMap::new();
+) -> std::io::Result<Report> {
+    Config::new().codegen(out_path, asset_dirs, extra_files)
+}
+
+/// The `Cache-Control` value of files whose URL changes with their content.
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// Lints allowed on every top-level generated item, so crates that deny
+/// warnings can include the file. Items in modules inherit them.
+const ALLOW_LINTS: &str = "#[allow(clippy::all, clippy::pedantic, clippy::nursery, missing_docs)]";
+
+/// A file rehashed by [`Config::codegen_changed`].
+struct ChangedFile {
+    /// Its canonical path, as in its static's `file_name`.
+    file_name: String,
+    /// Its path relative to its asset directory, as in the manifest.
+    rel_path: String,
+    url: String,
+    etag: Option<String>,
+}
+
+impl ChangedFile {
+    /// `code` with this file's old URL and ETag replaced, or `None` if it
+    /// doesn't have exactly one static for the file.
+    fn patch_code(&self, code: &str, url_form: UrlForm) -> Option<String> {
+        let marker = format!("    file_name: {:?},\n", self.file_name);
+        let start = code.find(&marker)?;
+        if code[start + marker.len()..].contains(&marker) {
+            return None;
+        }
+        let end = start + code[start..].find("};\n")?;
+        let field = |name: &str| {
+            code[start..end]
+                .lines()
+                .find_map(|line| line.trim_start().strip_prefix(name)?.strip_suffix(','))
+        };
+
+        let mut patched = code.to_string();
+        if let Some(etag) = &self.etag {
+            let old_etag = format!("etag: {},", field("etag: ")?);
+            let block = code[start..end].replace(&old_etag, &format!("etag: {etag:?},"));
+            patched.replace_range(start..end, &block);
+        }
+
+        // The URL is everywhere the file is looked up by it, and relative
+        // forms key the `match`-based `get` by the path from the root.
+        let old_url = field("name: ")?;
+        let new_url = format!("{:?}", self.url);
+        patched = patched.replace(old_url, &new_url);
+        if url_form != UrlForm::Absolute {
+            let key = |url: &str| {
+                format!(
+                    "\"{}",
+                    url[1..].trim_start_matches("./").trim_start_matches('/')
+                )
+            };
+            patched = patched.replace(&key(old_url), &key(&new_url));
+        }
+
+        Some(patched)
+    }
+}
+
+/// The result of walking the asset directories.
+struct Generated {
+    /// Every file's relative path and URL, sorted by path.
+    manifest: Manifest,
+    report: Report,
+    /// Every static's values, in the order they're generated.
+    #[cfg_attr(not(feature = "macros"), allow(dead_code))]
+    records: Vec<StaticRecord>,
+}
+
+/// Walk the asset directories into the Rust source of the statics file, and
+/// what was found. `out_path` is the file being written, which is skipped if
+/// it's inside one of them so it doesn't become an asset itself.
+fn generate(
+    config: &Config,
+    asset_dirs: &[PathBuf],
+    extra_files: &[PathBuf],
+    out_path: Option<&Path>,
+) -> std::io::Result<(String, Generated)> {
+    let mut code = Vec::new();
+    let generator = Generator::new(config, out_path, None)?;
+    let output = generate_to(&mut code, generator, asset_dirs, extra_files)?;
+    let code = String::from_utf8(code).expect("generated code is UTF-8");
+    Ok((code, output))
+}
+
+/// Walk the asset directories for just the manifest, without generating
+/// any code.
+fn generate_manifest(
+    config: &Config,
+    asset_dirs: &[PathBuf],
+    extra_files: &[PathBuf],
+    out_path: Option<&Path>,
+) -> std::io::Result<Manifest> {
+    let mut generator = Generator::new(config, out_path, None)?;
+    generator.manifest_only = true;
+    generator.walk(asset_dirs, extra_files)?;
+    Ok(generator.manifest)
+}
+
+/// Walk the asset directories with `generator`, eg. one reading files from
+/// an archive rather than the filesystem, writing the code to `writer` as
+/// it's generated. The `StaticFile` impls and `STATICS` follow the walk. If
+/// it fails, `writer` may have been given part of the code.
+fn generate_to<'a>(
+    writer: &'a mut dyn Write,
+    mut generator: Generator<'a>,
+    asset_dirs: &[PathBuf],
+    extra_files: &[PathBuf],
+) -> std::io::Result<Generated> {
+    let config = generator.config;
+    config.check_options()?;
+    generator.out = Some(Tidy::new(writer));
+
+    let header = match &config.header {
+        Some(header) => std::iter::once("// @generated by cacheb\n".to_string())
+            .chain(
+                header
+                    .lines()
+                    .map(|line| format!("// {line}").trim_end().to_string() + "\n"),
+            )
+            .collect(),
+        None => "// @generated by cacheb — do not edit\n".to_string(),
+    };
+    let derives = std::iter::once("Debug".to_string())
+        .chain(config.derive_names())
+        .collect::<Vec<_>>()
+        .join(", ");
+    generator.output.push_str(&format!(
+        r#"{header}
+{ALLOW_LINTS}
+#[derive({derives})]
+pub struct StaticFile {{
+"#,
+    ));
+    if config.has_field(Field::FileName) {
+        generator
+            .output
+            .push_str("    pub file_name: &'static str,\n");
+    }
+    generator
+        .output
+        .push_str("    pub name: &'static str,\n    pub mime: &'static str,\n");
+    if config.has_field(Field::Meta) {
+        generator
+            .output
+            .push_str("    pub meta: &'static [(&'static str, &'static str)],\n");
+    }
+    if config.has_field(Field::CacheControl) {
+        generator
+            .output
+            .push_str("    pub cache_control: &'static str,\n");
+    }
+    if config.serve_root {
+        generator
+            .output
+            .push_str("    pub rel_path: &'static str,\n");
+    }
+    if config.public_url_prefix.is_some() {
+        generator
+            .output
+            .push_str("    pub route: &'static str,\n    pub url: &'static str,\n");
+    }
+    if config.content_hashes {
+        generator.output.push_str("    pub hash: &'static str,\n");
+    }
+    if config.etag.is_some() {
+        generator.output.push_str("    pub etag: &'static str,\n");
+    }
+    if !config.downloads.is_empty() {
+        generator
+            .output
+            .push_str("    pub disposition: Option<&'static str>,\n");
+    }
+    if !config.preloads.is_empty() {
+        generator.output.push_str("    pub preload: bool,\n");
+    }
+    if config.alternates {
+        generator
+            .output
+            .push_str("    pub alternates: &'static [&'static StaticFile],\n");
+    }
+    if config.source_maps {
+        generator
+            .output
+            .push_str("    pub source_map: Option<&'static StaticFile>,\n");
+    }
+    if config.compress_embedded_enabled() {
+        generator.output.push_str(
+            "    compressed_content: &'static [u8],\n    content_cache: std::sync::OnceLock<Vec<u8>>,\n",
+        );
+    } else if config.embed {
+        generator
+            .output
+            .push_str("    pub content: &'static [u8],\n");
+    }
+    if config.gzip_enabled() {
+        generator
+            .output
+            .push_str("    pub gzip: Option<&'static [u8]>,\n");
+    }
+    if config.brotli_enabled() {
+        generator
+            .output
+            .push_str("    pub brotli: Option<&'static [u8]>,\n");
+    }
+    generator.output.push_str("}\n");
+
+    if config.kinds {
+        generator.output.push_str(&format!(
+            r#"
+/// What a `StaticFile` is used for, derived from its MIME type.
+{ALLOW_LINTS}
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StaticKind {{
+    Style,
+    Script,
+    Image,
+    Font,
+    Wasm,
+    Document,
+    Other,
+}}
+"#,
+        ));
+    }
+    if config.base_url_env.is_some() {
+        generator.output.push_str(&format!(
+            "\n{ALLOW_LINTS}\nstatic BASE_URL: std::sync::OnceLock<String> = std::sync::OnceLock::new();\n",
+        ));
+    }
+    if config.serve_root {
+        generator.output.push_str(&format!(
+            "\n{ALLOW_LINTS}\nstatic SERVE_ROOT: std::sync::RwLock<Option<std::path::PathBuf>> = std::sync::RwLock::new(None);\n",
+        ));
+    }
+    if config.generated_at {
+        generator.output.push_str(&format!(
+            "\n/// When this file was generated.\n{ALLOW_LINTS}\npub const GENERATED_AT: &str = \"{}\";\n",
+            rfc3339_now()
+        ));
+    }
+
+    generator.flush()?;
+    generator.walk(asset_dirs, extra_files)?;
+    generator.flush()?;
+
+    let Generator {
+        mut output,
+        out,
+        manifest,
+        hashes,
+        report,
+        static_files,
+        lookups,
+        phf_entries,
+        logical_names,
+        macro_arms,
+        indexes,
+        fallback,
+        reexports,
+        categories,
+        module_map,
+        asset_variants,
+        hoisted_bytes,
+        records,
+        ..
+    } = generator;
+
+    if let Some(category) = categories
+        .keys()
+        .find(|name| module_map.contains_key(**name))
+    {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "directory module `{category}` would clash with the category module of that name"
+            ),
+        ));
+    }
+
+    if fallback.is_none() {
+        if let Some(path) = &config.fallback {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("fallback {} is not one of the assets", path.display()),
+            ));
+        }
+    }
+    let get_miss = if config.index_files {
+        "Self::get_index(name)"
+    } else {
+        "None"
+    };
+    // Relative names match requests by their path from the root, whichever
+    // form the request has.
+    let relative = config.url_form != UrlForm::Absolute;
+    // The field pages link to, as opposed to the one requests match.
+    let link = if config.public_url_prefix.is_some() {
+        "url"
+    } else {
+        "name"
+    };
+    let request = |path: &str| {
+        if relative {
+            format!("relative_path({path})")
+        } else {
+            path.to_string()
+        }
+    };
+
+    let mut impls = String::new();
+    impls.push_str("\n#[allow(dead_code)]\nimpl StaticFile {");
+    if config.match_lookup {
+        let arms = lookups
+            .iter()
+            .map(|arm| format!("\n            {arm}"))
+            .collect::<String>();
+        impls.push_str(&format!(
+            r#"
+    /// Get a single `StaticFile` by name, if it exists.
+    #[must_use]
+    pub fn get(name: &str) -> Option<&'static Self> {{
+        let name = name.find(['?', '#']).map_or(name, |end| &name[..end]);
+        match {} {{{arms}
+            _ => {get_miss},
+        }}
+    }}
+"#,
+            request("name")
+        ));
+    } else if let Some(map) = phf_map(config, &phf_entries) {
+        impls.push_str(&format!(
+            r#"
+    /// Get a single `StaticFile` by name, if it exists.
+    #[must_use]
+    pub fn get(name: &str) -> Option<&'static Self> {{
+        static LOOKUP: phf::Map<&'static str, &'static StaticFile> = {map};
+        let name = name.find(['?', '#']).map_or(name, |end| &name[..end]);
+        match LOOKUP.get({}) {{
+            Some(&file) => Some(file),
+            None => {get_miss},
+        }}
+    }}
+"#,
+            request("name")
+        ));
+    } else if config.lazy_lookup {
+        impls.push_str(&format!(
+            r#"
+    /// Get a single `StaticFile` by name, if it exists.
+    #[must_use]
+    pub fn get(name: &str) -> Option<&'static Self> {{
+        static LOOKUP: std::sync::OnceLock<
+            std::collections::HashMap<&'static str, &'static StaticFile>,
+        > = std::sync::OnceLock::new();
+        let lookup = LOOKUP.get_or_init(|| {{
+            STATICS.iter().map(|&file| ({}, file)).collect()
+        }});
+        let name = name.find(['?', '#']).map_or(name, |end| &name[..end]);
+        match lookup.get({}) {{
+            Some(&file) => Some(file),
+            None => {get_miss},
+        }}
+    }}
+"#,
+            request("file.name"),
+            request("name")
+        ));
+    } else {
+        impls.push_str(&format!(
+            r#"
+    /// Get a single `StaticFile` by name, if it exists.
+    #[must_use]
+    pub fn get(name: &str) -> Option<&'static Self> {{
+        if let Some(pos) = STATICS.iter().position(|s| s.matches_request(name)) {{
+            Some(STATICS[pos])
+        }} else {{
+            {get_miss}
+        }}
+    }}
+"#
+        ));
+    }
+    impls.push_str(&format!(
+        r#"
+    /// Whether a request path refers to this file, ignoring any query string
+    /// or fragment.
+    #[must_use]
+    pub fn matches_request(&self, path: &str) -> bool {{
+        let path = path.find(['?', '#']).map_or(path, |end| &path[..end]);
+        {} == {}
+    }}
+"#,
+        request("path"),
+        request("self.name")
+    ));
+    if let Some(fallback) = &fallback {
+        let fallback = fallback.trim_start_matches('&');
+        impls.push_str(&format!(
+            r#"
+    /// Get a single `StaticFile` by name, or the fallback asset if it doesn't
+    /// exist.
+    #[must_use]
+    pub fn get_or_404(name: &str) -> &'static Self {{
+        Self::get(name).unwrap_or(&super::{fallback})
+    }}
+"#
+        ));
+    }
+    if !config.embed
+        && !config.compress_embedded_enabled()
+        && !config.no_std
+        && config.has_field(Field::FileName)
+    {
+        let disk_path = if config.serve_root {
+            "file.path()"
+        } else {
+            "file.file_name"
+        };
+        impls.push_str(&format!(
+            r#"
+    /// Check that every static's file is still on disk, eg. at the start of
+    /// a dev server, returning the `file_name` of each one that's missing.
+    pub fn validate_sources() -> Result<(), Vec<&'static str>> {{
+        let missing: Vec<&'static str> = STATICS
+            .iter()
+            .filter(|file| !std::path::Path::new(&{disk_path}).is_file())
+            .map(|file| file.file_name)
+            .collect();
+        if missing.is_empty() {{
+            Ok(())
+        }} else {{
+            Err(missing)
+        }}
+    }}
+"#
+        ));
+    }
+    if let Some((mime, body)) = &config.not_found {
+        impls.push_str(&format!(
+            r#"
+    /// The content type of `NOT_FOUND_BODY`.
+    pub const NOT_FOUND_MIME: &'static str = {mime:?};
+
+    /// The body served with `404 Not Found` for unknown assets.
+    pub const NOT_FOUND_BODY: &'static [u8] = {};
+"#,
+            byte_string(body)
+        ));
+    }
+    if config.compress_embedded_enabled() {
+        impls.push_str(
+            r#"
+    /// This file's contents, decompressed on first access and cached after.
+    #[must_use]
+    pub fn content(&'static self) -> &'static [u8] {
+        self.content_cache.get_or_init(|| {
+            let mut content = Vec::new();
+            brotli::BrotliDecompress(&mut &self.compressed_content[..], &mut content)
+                .expect("embedded content is valid brotli");
+            content
+        })
+    }
+"#,
+        );
+    }
+    let (receiver, content) = if config.compress_embedded_enabled() {
+        ("&'static self", "self.content()")
+    } else {
+        ("&self", "self.content")
+    };
+    if config.embed || config.compress_embedded_enabled() {
+        impls.push_str(&format!(
+            r#"
+    /// This file's MIME type and contents, eg. for inlining critical CSS
+    /// into a page's `<head>`.
+    #[must_use]
+    pub fn inline({receiver}) -> (&'static str, &'static [u8]) {{
+        (self.mime, {content})
+    }}
+"#
+        ));
+    }
+    if (config.embed || config.compress_embedded_enabled())
+        && (config.gzip_enabled() || config.brotli_enabled())
+    {
+        impls.push_str(&format!(
+            r#"
+    /// The best available encoding of this file for an `Accept-Encoding`
+    /// header value, preferring brotli over gzip over identity, along with
+    /// the `Content-Encoding` to send with it, if any.
+    #[must_use]
+    pub fn best_variant({receiver}, accept_encoding: &str) -> (&'static [u8], Option<&'static str>) {{"#
+        ));
+        impls.push_str(
+            r#"
+        let accepts = |encoding: &str| {
+            let mut wildcard = None;
+            for item in accept_encoding.split(',') {
+                let mut params = item.split(';');
+                let name = params.next().unwrap_or_default().trim();
+                let quality = params
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .and_then(|quality| quality.trim().parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                if name.eq_ignore_ascii_case(encoding) {
+                    return quality > 0.0;
+                }
+                if name == "*" {
+                    wildcard = Some(quality);
+                }
+            }
+            wildcard.is_some_and(|quality| quality > 0.0)
+        };
+"#,
+        );
+        if config.brotli_enabled() {
+            impls.push_str(
+                r#"
+        if let Some(brotli) = self.brotli.filter(|_| accepts("br")) {
+            return (brotli, Some("br"));
+        }
+"#,
+            );
+        }
+        if config.gzip_enabled() {
+            impls.push_str(
+                r#"
+        if let Some(gzip) = self.gzip.filter(|_| accepts("gzip")) {
+            return (gzip, Some("gzip"));
+        }
+"#,
+            );
+        }
+        impls.push_str(&format!(
+            r#"
+        ({content}, None)
+    }}
+"#
+        ));
+    }
+    if config.axum_enabled() {
+        let (etag_header, not_modified) = if config.etag.is_some() {
+            (
+                "\n                (header::ETAG, self.etag),",
+                r#"
+        let not_modified = headers
+            .get_all(header::IF_NONE_MATCH)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == self.etag || tag == "*");
+        if not_modified {
+            return (
+                StatusCode::NOT_MODIFIED,
+                [(header::ETAG, self.etag), (header::CACHE_CONTROL, self.cache_control)],
+            )
+                .into_response();
+        }
+"#,
+            )
+        } else {
+            ("", "")
+        };
+        let not_found = if config.not_found.is_some() {
+            r#"(
+            axum::http::StatusCode::NOT_FOUND,
+            [(axum::http::header::CONTENT_TYPE, Self::NOT_FOUND_MIME)],
+            Self::NOT_FOUND_BODY,
+        )"#
+        } else {
+            "axum::http::StatusCode::NOT_FOUND"
+        };
+        let (bind, disposition) = if config.downloads.is_empty() {
+            ("", "")
+        } else {
+            (
+                "let mut response = ",
+                r#";
+        if let Some(disposition) = self.disposition {
+            response.headers_mut().insert(
+                header::CONTENT_DISPOSITION,
+                axum::http::HeaderValue::from_static(disposition),
+            );
+        }
+        response"#,
+            )
+        };
+        impls.push_str(&format!(
+            r#"
+    /// A response serving this file with its content type and cache headers,
+    /// or `304 Not Modified` if `headers` hold an `If-None-Match` matching its
+    /// ETag.
+    #[allow(unused_variables)]
+    pub fn response({receiver}, headers: &axum::http::HeaderMap) -> axum::response::Response {{
+        use axum::http::{{header, StatusCode}};
+        use axum::response::IntoResponse;
+{not_modified}
+        {bind}(
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, self.mime),{etag_header}
+                (header::CACHE_CONTROL, self.cache_control),
+            ],
+            {content},
+        )
+            .into_response(){disposition}
+    }}
+
+    /// A response serving the static at `path` as `response` does, or
+    /// `StaticFile::not_found_response` if there is none.
+    pub fn serve(path: &str, headers: &axum::http::HeaderMap) -> axum::response::Response {{
+        match Self::get(path) {{
+            Some(file) => file.response(headers),
+            None => Self::not_found_response(),
+        }}
+    }}
+
+    /// The `404 Not Found` response for an unknown asset.
+    pub fn not_found_response() -> axum::response::Response {{
+        use axum::response::IntoResponse;
+        {not_found}.into_response()
+    }}
+"#
+        ));
+    }
+    if config.http_enabled() {
+        let (etag_header, not_modified) = if config.etag.is_some() {
+            (
+                "\n            .header(header::ETAG, self.etag)",
+                r#"
+        let not_modified = headers
+            .get_all(header::IF_NONE_MATCH)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == self.etag || tag == "*");
+        if not_modified {
+            return http::Response::builder()
+                .status(http::StatusCode::NOT_MODIFIED)
+                .header(header::ETAG, self.etag)
+                .header(header::CACHE_CONTROL, self.cache_control)
+                .body(bytes::Bytes::new())
+                .expect("static headers are valid");
+        }
+"#,
+            )
+        } else {
+            ("", "")
+        };
+        let disposition = if config.downloads.is_empty() {
+            ""
+        } else {
+            r#"
+        if let Some(disposition) = self.disposition {
+            response = response.header(header::CONTENT_DISPOSITION, disposition);
+        }"#
+        };
+        impls.push_str(&format!(
+            r#"
+    /// An `http` response serving this file with its content type, length
+    /// and cache headers, or `304 Not Modified` if `headers` hold an
+    /// `If-None-Match` matching its ETag.
+    #[allow(unused_variables)]
+    pub fn into_http_response(
+        {receiver},
+        headers: &http::HeaderMap,
+    ) -> http::Response<bytes::Bytes> {{
+        use http::header;
+{not_modified}
+        let content = {content};
+        #[allow(unused_mut)]
+        let mut response = http::Response::builder()
+            .status(http::StatusCode::OK)
+            .header(header::CONTENT_TYPE, self.mime)
+            .header(header::CONTENT_LENGTH, content.len()){etag_header}
+            .header(header::CACHE_CONTROL, self.cache_control);{disposition}
+        response
+            .body(bytes::Bytes::from_static(content))
+            .expect("static headers are valid")
+    }}
+"#
+        ));
+        // Named apart from axum's `not_found_response`, which it sits beside
+        // when both are enabled.
+        let not_found = if config.not_found.is_some() {
+            r#"
+            .header(http::header::CONTENT_TYPE, Self::NOT_FOUND_MIME)
+            .header(http::header::CONTENT_LENGTH, Self::NOT_FOUND_BODY.len())
+            .body(bytes::Bytes::from_static(Self::NOT_FOUND_BODY))"#
+        } else {
+            "\n            .body(bytes::Bytes::new())"
+        };
+        impls.push_str(&format!(
+            r#"
+    /// The `http` `404 Not Found` response for an unknown asset.
+    pub fn not_found_http_response() -> http::Response<bytes::Bytes> {{
+        http::Response::builder()
+            .status(http::StatusCode::NOT_FOUND){not_found}
+            .expect("static headers are valid")
+    }}
+"#
+        ));
+    }
+    if config.rocket_enabled() {
+        let (catcher, register) = if config.not_found.is_some() {
+            impls.push_str(
+                r#"
+    /// A Rocket catcher answering `404 Not Found` with `NOT_FOUND_BODY`.
+    #[must_use]
+    pub fn not_found_catcher() -> rocket::Catcher {
+        fn handle<'r>(
+            status: rocket::http::Status,
+            _request: &'r rocket::Request<'_>,
+        ) -> rocket::catcher::BoxFuture<'r> {
+            let body = StaticFile::NOT_FOUND_BODY;
+            let mut response = rocket::Response::build();
+            response
+                .status(status)
+                .raw_header("Content-Type", StaticFile::NOT_FOUND_MIME)
+                .sized_body(body.len(), std::io::Cursor::new(body));
+            Box::pin(std::future::ready(response.ok()))
+        }
+
+        rocket::Catcher::new(404, handle)
+    }
+"#,
+            );
+            (
+                " and registering `StaticFile::not_found_catcher`",
+                "\n                .register(\"/\", vec![Self::not_found_catcher()])",
+            )
+        } else {
+            ("", "")
+        };
+        impls.push_str(&format!(
+            r#"
+    /// A Rocket route serving every static at its URL, which forwards
+    /// requests for anything else. Mount it at `/`, or attach
+    /// `StaticFile::fairing` to do so.
+    #[must_use]
+    pub fn rocket_route() -> rocket::Route {{
+        fn handle<'r>(
+            request: &'r rocket::Request<'_>,
+            data: rocket::Data<'r>,
+        ) -> rocket::route::BoxFuture<'r> {{
+            let path = request.uri().path().raw().percent_decode_lossy();
+            match StaticFile::get(&path) {{
+                Some(file) => rocket::route::Outcome::from(request, file).pin(),
+                None => rocket::route::Outcome::forward(data, rocket::http::Status::NotFound).pin(),
+            }}
+        }}
+
+        rocket::Route::new(rocket::http::Method::Get, "/<path..>", handle)
+    }}
+
+    /// A Rocket fairing mounting `StaticFile::rocket_route` at `/`{catcher}.
+    #[must_use]
+    pub fn fairing() -> rocket::fairing::AdHoc {{
+        rocket::fairing::AdHoc::on_ignite("cacheb statics", |rocket| async move {{
+            rocket.mount("/", vec![Self::rocket_route()]){register}
+        }})
+    }}
+"#
+        ));
+    }
+    if let Some(var) = &config.base_url_env {
+        impls.push_str(&format!(
+            r#"
+    /// Set the base URL `url` prefixes names with, instead of reading it from
+    /// `{var}`. Fails with the given base if one is already in use.
+    pub fn set_base_url(base: impl Into<String>) -> Result<(), String> {{
+        BASE_URL.set(base.into())
+    }}
+
+    /// This file's URL under the runtime base URL, eg.
+    /// `https://cdn.example.com/static/app-1a2b3c.js`, or just its {link} if
+    /// neither `set_base_url` nor `{var}` provided one.
+    #[must_use]
+    pub fn url(&self) -> String {{
+        let base = BASE_URL.get_or_init(|| std::env::var({var:?}).unwrap_or_default());
+        {join}
+    }}
+"#,
+            join = if relative {
+                format!("if base.is_empty() {{\n            return self.{link}.to_string();\n        }}\n        format!(\"{{}}/{{}}\", base.trim_end_matches('/'), relative_path(self.{link}))")
+            } else {
+                format!("format!(\"{{}}{{}}\", base.trim_end_matches('/'), self.{link})")
+            }
+        ));
+    }
+    if config.serve_root {
+        impls.push_str(
+            r#"
+    /// Read files from under `root` instead of from `file_name`, eg. a copy
+    /// of the asset directories in tests, or from `file_name` again with
+    /// `None`. Applies to every thread.
+    pub fn set_serve_root(root: Option<std::path::PathBuf>) {
+        *SERVE_ROOT.write().unwrap_or_else(|err| err.into_inner()) = root;
+    }
+
+    /// Where this file is read from: under the serve root if one is set,
+    /// otherwise `file_name`.
+    #[must_use]
+    pub fn path(&self) -> std::path::PathBuf {
+        match &*SERVE_ROOT.read().unwrap_or_else(|err| err.into_inner()) {
+            Some(root) => self.path_in(root),
+            None => std::path::PathBuf::from(self.file_name),
+        }
+    }
+
+    /// Where this file is under `root`, for handlers that take their
+    /// directory as a parameter instead of using the serve root.
+    #[must_use]
+    pub fn path_in(&self, root: &std::path::Path) -> std::path::PathBuf {
+        root.join(self.rel_path)
+    }
+
+    /// Read this file's contents from its `path`.
+    pub fn read(&self) -> std::io::Result<Vec<u8>> {
+        std::fs::read(self.path())
+    }
+"#,
+        );
+    }
+    if config.kinds {
+        impls.push_str(
+            r#"
+    /// What this file is used for, derived from its MIME type.
+    #[must_use]
+    pub fn kind(&self) -> StaticKind {
+        match self.mime {
+            "text/css" => StaticKind::Style,
+            "application/javascript" | "text/javascript" => StaticKind::Script,
+            "application/wasm" => StaticKind::Wasm,
+            "text/html" => StaticKind::Document,
+            mime if mime.starts_with("image/") => StaticKind::Image,
+            mime if mime.starts_with("font/") => StaticKind::Font,
+            _ => StaticKind::Other,
+        }
+    }
+
+    /// Every file, in the same order as `STATICS`.
+    pub fn all() -> impl Iterator<Item = &'static Self> {
+        STATICS.iter().copied()
+    }
+
+    /// Every file of the given kind, eg. `StaticKind::Style` for preloading
+    /// stylesheets.
+    pub fn by_kind(kind: StaticKind) -> impl Iterator<Item = &'static Self> {
+        Self::all().filter(move |file| file.kind() == kind)
+    }
+"#,
+        );
+    }
+    if config.routes {
+        impls.push_str(
+            r#"
+    /// Every file as a `(method, path, file)` route to register with a router.
+    pub fn routes() -> impl Iterator<Item = (&'static str, &'static str, &'static Self)> {
+        STATICS.iter().map(|&file| ("GET", file.name, file))
+    }
+"#,
+        );
+    }
+    if config.content_hashes {
+        impls.push_str(
+            r#"
+    /// The file whose `hash` is `hash`, or the first in `STATICS` if
+    /// truncated hashes make several share it.
+    #[must_use]
+    pub fn find_by_hash(hash: &str) -> Option<&'static Self> {
+        STATICS.iter().copied().find(|file| file.hash == hash)
+    }
+"#,
+        );
+    }
+    if config.resolve {
+        let arms = logical_names
+            .iter()
+            .map(|arm| format!("\n            {arm}"))
+            .collect::<String>();
+        impls.push_str(&format!(
+            r#"
+    /// The hashed URL of the file at `logical`, relative to its asset
+    /// directory, eg. `vendor/script.js`.
+    #[must_use]
+    pub fn resolve(logical: &str) -> Option<&'static str> {{
+        match logical {{{arms}
+            _ => None,
+        }}
+    }}
+"#
+        ));
+    }
+    if config.index_files {
+        impls.push_str(&format!(
+            r#"
+    /// Get the `index.html` of the directory a request path refers to, eg.
+    /// `/static/docs/`, if it has one.
+    #[must_use]
+    pub fn get_index(name: &str) -> Option<&'static Self> {{
+        let name = name.find(['?', '#']).map_or(name, |end| &name[..end]);
+        INDEXES
+            .iter()
+            .find(|(dir, _)| {} == {})
+            .map(|(_, index)| *index)
+    }}
+"#,
+            request("name"),
+            request("*dir")
+        ));
+    }
+    if !config.preloads.is_empty() {
+        impls.push_str(
+            r#"
+    /// A `<link rel="preload">` tag for this file if it's marked for
+    /// preloading, `None` otherwise.
+    #[must_use]
+    pub fn preload_link(&self) -> Option<String> {
+        if !self.preload {
+            return None;
+        }
+        let mime = self.mime.split(';').next().unwrap_or_default().trim();
+        let (destination, crossorigin) = match mime {
+            "text/css" => ("style", ""),
+            "application/javascript" | "text/javascript" => ("script", ""),
+            mime if mime.starts_with("font/") => ("font", " crossorigin"),
+            mime if mime.starts_with("image/") => ("image", ""),
+            _ => ("fetch", " crossorigin"),
+        };
+        Some(format!(
+            "<link rel=\"preload\" href=\"{self}\" as=\"{destination}\"{crossorigin}>"
+        ))
+    }
+"#,
+        );
+    }
+    impls.push_str("}\n");
+    if relative {
+        impls.push_str(
+            r#"
+/// `path` without its leading `/` or `./`.
+fn relative_path(path: &str) -> &str {
+    path.strip_prefix("./")
+        .or_else(|| path.strip_prefix('/'))
+        .unwrap_or(path)
+}
+"#,
+        );
+    }
+    // `core` works in `std` crates too, but their readers expect `std`.
+    let core = if config.no_std { "core" } else { "std" };
+    impls.push_str(&format!(
+        r#"
+impl {core}::fmt::Display for StaticFile {{
+    fn fmt(&self, f: &mut {core}::fmt::Formatter<'_>) -> {core}::fmt::Result {{
+        write!(f, "{{}}", self.{link})
+    }}
+}}
+
+impl PartialEq for StaticFile {{
+    fn eq(&self, other: &Self) -> bool {{
+        self.name == other.name
+    }}
+}}
+
+impl Eq for StaticFile {{}}
+
+impl {core}::hash::Hash for StaticFile {{
+    fn hash<H: {core}::hash::Hasher>(&self, state: &mut H) {{
+        self.name.hash(state);
+    }}
+}}
+"#,
+    ));
+    if config.axum_enabled() {
+        impls.push_str(
+            r#"
+impl axum::response::IntoResponse for &'static StaticFile {
+    fn into_response(self) -> axum::response::Response {
+        self.response(&axum::http::HeaderMap::new())
+    }
+}
+"#,
+        );
+    }
+
+    if config.rocket_enabled() {
+        let not_modified = if config.etag.is_some() {
+            r#"
+        response.raw_header("ETag", self.etag);
+        let not_modified = request
+            .headers()
+            .get("If-None-Match")
+            .flat_map(|value| value.split(','))
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == self.etag || tag == "*");
+        if not_modified {
+            return response.status(rocket::http::Status::NotModified).ok();
+        }
+"#
+        } else {
+            ""
+        };
+        let disposition = if config.downloads.is_empty() {
+            ""
+        } else {
+            r#"
+        if let Some(disposition) = self.disposition {
+            response.raw_header("Content-Disposition", disposition);
+        }"#
+        };
+        let body = if config.embed || config.compress_embedded_enabled() {
+            format!("response.sized_body({content}.len(), std::io::Cursor::new({content}));")
+        } else {
+            let disk_path = if config.serve_root {
+                "self.path()"
+            } else {
+                "self.file_name"
+            };
+            format!(
+                r#"let file = std::fs::File::open({disk_path})
+            .map_err(|_| rocket::http::Status::NotFound)?;
+        response.sized_body(None, rocket::tokio::fs::File::from_std(file));"#
+            )
+        };
+        impls.push_str(&format!(
+            r#"
+impl<'r> rocket::response::Responder<'r, 'static> for &'static StaticFile {{
+    #[allow(unused_variables)]
+    fn respond_to(self, request: &'r rocket::Request<'_>) -> rocket::response::Result<'static> {{
+        let mut response = rocket::Response::build();
+        response.raw_header("Cache-Control", self.cache_control);{not_modified}
+        response.raw_header("Content-Type", self.mime);{disposition}
+        {body}
+        response.ok()
+    }}
+}}
+"#
+        ));
+    }
+
+    // The impls live in a module of their own that imports only what they
+    // use, so statics can't clash with the names of their locals.
+    let mut imports = vec!["StaticFile", "STATICS"];
+    if config.index_files {
+        imports.push("INDEXES");
+    }
+    if config.kinds {
+        imports.push("StaticKind");
+    }
+    if config.base_url_env.is_some() {
+        imports.push("BASE_URL");
+    }
+    if config.serve_root {
+        imports.push("SERVE_ROOT");
+    }
+    output.push_str(&format!(
+        "\n{ALLOW_LINTS}\nmod static_file_impls {{\n    use super::{{{}}};\n",
+        imports.join(", ")
+    ));
+    for line in impls.lines() {
+        if !line.is_empty() {
+            output.push_str("    ");
+        }
+        output.push_str(line);
+        output.push('\n');
+    }
+    output.push_str("}\n");
+
+    if !reexports.is_empty() {
+        output.push('\n');
+        for reexport in &reexports {
+            output.push_str(&format!("{ALLOW_LINTS}\n{reexport}\n"));
+        }
+    }
+
+    for (category, uses) in &categories {
+        output.push_str(&format!("\n{ALLOW_LINTS}\npub mod {category} {{\n"));
+        for (_, reexport) in uses {
+            output.push_str(&format!("    {reexport}\n"));
+        }
+        output.push_str("}\n");
+    }
+
+    output.push_str(&format!(
+        "\n{ALLOW_LINTS}\npub static STATICS: &[&StaticFile] = {};\n",
+        array_literal(&static_files)
+    ));
+
+    for bytes in &hoisted_bytes {
+        output.push_str(&format!("\n{ALLOW_LINTS}\n{bytes}\n"));
+    }
+
+    if config.asset_enum {
+        let variants = asset_variants
+            .iter()
+            .map(|(variant, cfg_prefix, _)| format!("\n    {cfg_prefix}{variant},"))
+            .collect::<String>();
+        let arms = asset_variants
+            .iter()
+            .map(|(variant, cfg_prefix, reference)| {
+                format!("\n            {cfg_prefix}Self::{variant} => {reference},")
+            })
+            .collect::<String>();
+        output.push_str(&format!(
+            r#"
+/// Every static, eg. for a `match` that must handle all of them.
+{ALLOW_LINTS}
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Asset {{{variants}
+}}
+
+{ALLOW_LINTS}
+impl Asset {{
+    /// The static this variant stands for.
+    #[must_use]
+    pub fn file(&self) -> &'static StaticFile {{
+        match *self {{{arms}
+        }}
+    }}
+}}
+"#
+        ));
+    }
+
+    if config.url_macro {
+        let arms = macro_arms
+            .iter()
+            .map(|arm| format!("\n    {arm}"))
+            .collect::<String>();
+        output.push_str(&format!(
+            r#"
+/// The hashed URL of a file, by its path relative to its asset directory.
+{ALLOW_LINTS}
+#[allow(unused_macros)]
+macro_rules! static_url {{{arms}
+    ($path:literal) => {{
+        compile_error!(concat!("no static file at ", $path))
+    }};
+}}
+{ALLOW_LINTS}
+#[allow(unused_imports)]
+pub(crate) use static_url;
+"#
+        ));
+    }
+
+    if config.index_files {
+        output.push_str(&format!(
+            "\n{ALLOW_LINTS}\nstatic INDEXES: &[(&str, &StaticFile)] = {};\n",
+            array_literal(&indexes)
+        ));
+    }
+
+    if let Some(mut out) = out {
+        out.write(&output)?;
+        out.finish()?;
+    }
+
+    Ok(Generated {
+        manifest,
+        report: Report {
+            hashes: Manifest::new(hashes),
+            ..report
+        },
+        records,
+    })
+}
+
+/// A slice literal of `items`, one per line with trailing commas as rustfmt
+/// lays them out.
+fn array_literal(items: &[String]) -> String {
+    if items.is_empty() {
+        return "&[]".to_string();
+    }
+    let items = items
+        .iter()
+        .map(|item| format!("\n    {item},"))
+        .collect::<String>();
+    format!("&[{items}\n]")
+}
+
+/// Writes generated code a section at a time, with runs of blank lines
+/// collapsed into one and none at either end, so sections are evenly spaced
+/// and the file ends in a single newline.
+struct Tidy<'w> {
+    writer: &'w mut dyn Write,
+    /// The end of the code so far, after its last newline.
+    partial: String,
+    /// Whether a line has been written.
+    started: bool,
+    /// Whether blank lines came since the last line written.
+    blank: bool,
+}
+
+impl<'w> Tidy<'w> {
+    fn new(writer: &'w mut dyn Write) -> Self {
+        Tidy {
+            writer,
+            partial: String::new(),
+            started: false,
+            blank: false,
+        }
+    }
+
+    /// Write the complete lines of `code`, keeping any unfinished last one
+    /// for the next section.
+    fn write(&mut self, code: &str) -> std::io::Result<()> {
+        self.partial.push_str(code);
+        let Some(end) = self.partial.rfind('\n') else {
+            return Ok(());
+        };
+        let lines = self.partial.drain(..=end).collect::<String>();
+        let mut tidy = String::with_capacity(lines.len());
+        for line in lines.lines() {
+            self.push_line(&mut tidy, line);
+        }
+        self.writer.write_all(tidy.as_bytes())
+    }
+
+    /// Write the unfinished last line, if any.
+    fn finish(mut self) -> std::io::Result<()> {
+        let line = std::mem::take(&mut self.partial);
+        let mut tidy = String::new();
+        self.push_line(&mut tidy, &line);
+        self.writer.write_all(tidy.as_bytes())
+    }
+
+    fn push_line(&mut self, tidy: &mut String, line: &str) {
+        if line.trim().is_empty() {
+            self.blank = self.started;
+        } else {
+            if self.blank {
+                tidy.push('\n');
+            }
+            tidy.push_str(line);
+            tidy.push('\n');
+            self.started = true;
+            self.blank = false;
+        }
+    }
+}
+
+/// Write `contents` to `path` unless it already holds exactly that, so
+/// unchanged output doesn't bump its modification time. Returns whether the
+/// file was written.
+fn write_if_changed(path: &Path, contents: &[u8]) -> std::io::Result<bool> {
+    if fs::read(path).is_ok_and(|existing| existing == contents) {
+        return Ok(false);
+    }
+
+    let mut out_file = File::create(path)?;
+    out_file.write_all(contents)?;
+
+    Ok(true)
+}
+
+/// Accumulates the generated code while walking the asset directories.
+struct Generator<'a> {
+    config: &'a Config,
+    output: String,
+    manifest: Manifest,
+    hashes: BTreeMap<String, String>,
+    report: Report,
+    static_files: Vec<String>,
+    /// The `match` arm for every static in the `match`-based `get`.
+    lookups: Vec<String>,
+    /// The keys and values of the `phf::Map` for [`Config::phf_lookup`].
+    phf_entries: Vec<(String, String)>,
+    /// The `match` arm for every static in `resolve`.
+    logical_names: Vec<String>,
+    /// The rule for every static in the `static_url!` macro.
+    macro_arms: Vec<String>,
+    module_map: HashMap<String, Vec<String>>,
+    indexes: Vec<String>,
+    fallback_path: Option<PathBuf>,
+    fallback: Option<String>,
+    /// The file behind every generated `module/ident`, statics and consts
+    /// alike, to catch collisions.
+    idents: HashMap<String, PathBuf>,
+    /// The path and file behind every lowercased URL prefix and relative
+    /// path, to catch collisions on case-insensitive filesystems.
+    lowercase_paths: HashMap<String, (String, PathBuf)>,
+    /// The `pub use` for every static in a directory module.
+    reexports: Vec<String>,
+    /// The name and `pub use` of every static in each category module.
+    categories: BTreeMap<&'static str, Vec<(String, String)>>,
+    /// The `Asset` variant of every static, with its `#[cfg]` prefix and
+    /// reference.
+    asset_variants: Vec<(String, String, String)>,
+    /// Every top-level `BYTES_` static holding [hoisted](Config::hoist_bytes)
+    /// bytes.
+    hoisted_bytes: Vec<String>,
+    /// The values of every static so far, in the order they're generated.
+    records: Vec<StaticRecord>,
+    /// The relative path and URL hash digest of every file so far, for
+    /// [directory hashes](Config::dir_hashes).
+    dir_digests: Vec<(PathBuf, Vec<u8>)>,
+    /// The canonical path of the file being generated, if it exists yet.
+    out_path: Option<PathBuf>,
+    /// The canonical directory of the file being generated.
+    out_dir: Option<PathBuf>,
+    /// The files of the archive being generated from, by their path in it,
+    /// which stand in for the filesystem.
+    entries: Option<&'a BTreeMap<PathBuf, Vec<u8>>>,
+    /// Whether only the manifest is wanted, so no code is generated.
+    manifest_only: bool,
+    /// Where each finished section of `output` goes, if anywhere.
+    out: Option<Tidy<'a>>,
+}
+
+/// The values a static is generated with, for building it as tokens with
+/// [`Config::token_stream`] rather than as source text.
+#[derive(Debug)]
+#[cfg_attr(not(feature = "macros"), allow(dead_code))]
+struct StaticRecord {
+    /// The path of its module, eg. `app/admin`, or empty at the top level.
+    module_path: String,
+    ident: String,
+    /// The lines of its doc comment.
+    doc: Vec<String>,
+    file_name: String,
+    name: String,
+    mime: String,
+    meta: Vec<(String, String)>,
+    cache_control: String,
+    hash: String,
+    /// Its quoted ETag, or empty without [`Config::etag`].
+    etag: String,
+    disposition: Option<String>,
+    preload: bool,
+}
+
+/// A precompressed variant of a file.
+struct Variant {
+    /// Its `StaticFile` field, eg. `brotli`.
+    field: &'static str,
+    /// Its `Content-Encoding`, eg. `br`.
+    content_encoding: &'static str,
+    /// The compressed content, unless it isn't smaller than the file.
+    compressed: Option<Vec<u8>>,
+}
+
+/// Files in the same directory that a file links to.
+#[derive(Debug, Default)]
+struct Related {
+    /// The identifiers of its [alternates](Config::alternates).
+    alternates: Vec<String>,
+    /// The identifier and path of its [source map](Config::source_maps).
+    source_map: Option<(String, PathBuf)>,
+}
+
+impl<'a> Generator<'a> {
+    fn new(
+        config: &'a Config,
+        out_path: Option<&Path>,
+        entries: Option<&'a BTreeMap<PathBuf, Vec<u8>>>,
+    ) -> std::io::Result<Self> {
+        let fallback_path = match (&config.fallback, entries) {
+            (Some(path), Some(_)) => Some(path.clone()),
+            (fallback, _) => fallback.as_ref().map(fs::canonicalize).transpose()?,
+        };
+        Ok(Generator {
+            config,
+            output: String::new(),
+            manifest: Manifest::default(),
+            hashes: BTreeMap::new(),
+            report: Report::default(),
+            static_files: Vec::new(),
+            lookups: Vec::new(),
+            phf_entries: Vec::new(),
+            logical_names: Vec::new(),
+            macro_arms: Vec::new(),
+            module_map: HashMap::new(),
+            indexes: Vec::new(),
+            fallback_path,
+            fallback: None,
+            idents: HashMap::new(),
+            lowercase_paths: HashMap::new(),
+            reexports: Vec::new(),
+            categories: BTreeMap::new(),
+            asset_variants: Vec::new(),
+            hoisted_bytes: Vec::new(),
+            records: Vec::new(),
+            dir_digests: Vec::new(),
+            out_path: out_path.and_then(|path| fs::canonicalize(path).ok()),
+            out_dir: out_path.and_then(|path| {
+                let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+                fs::canonicalize(dir.unwrap_or(Path::new("."))).ok()
+            }),
+            entries,
+            manifest_only: false,
+            out: None,
+        })
+    }
+
+    /// Write the code generated so far, so a large tree's isn't all held in
+    /// memory at once.
+    fn flush(&mut self) -> std::io::Result<()> {
+        if let Some(out) = &mut self.out {
+            out.write(&self.output)?;
+        }
+        self.output.clear();
+        Ok(())
+    }
+
+    /// Walk `asset_dirs` and `extra_files`, generating each file.
+    fn walk(&mut self, asset_dirs: &[PathBuf], extra_files: &[PathBuf]) -> std::io::Result<()> {
+        let roots = distinct_asset_dirs(asset_dirs)
+            .into_iter()
+            .map(|asset_dir| (asset_dir.clone(), asset_dir.clone()))
+            .collect::<Vec<_>>();
+        if self.config.overlay {
+            if !roots.is_empty() {
+                self.process_directory(&roots, 0)?;
+            }
+        } else {
+            for root in roots {
+                self.process_directory(&[root], 0)?;
+            }
+        }
+
+        if let Some((_, logical_path)) =
+            self.config.logical_paths.iter().find(|(_, logical_path)| {
+                !logical_path
+                    .components()
+                    .all(|component| matches!(component, std::path::Component::Normal(_)))
+            })
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "logical path {} isn't a relative path without `..`",
+                    logical_path.display()
+                ),
+            ));
+        }
+
+        // Extra files with a logical path in a directory are generated after
+        // the rest, each directory's files in one module.
+        let mut nested = Vec::new();
+        for file_path in extra_files {
+            let portable = portable_path(file_path);
+            let mut var_name = file_ident(
+                self.config.logical_path_of(&portable).unwrap_or(file_path),
+                self.config.ident_strategy,
+            )?;
+            let parent = file_path.parent().unwrap_or(Path::new(""));
+            let rel_dir = self
+                .config
+                .relative_path(&portable, parent)
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default();
+            if rel_dir.as_os_str().is_empty() {
+                self.process_file(file_path, parent, var_name, &Related::default(), 0)?;
+            } else if self.config.flat {
+                var_name = format!(
+                    "{}_{var_name}",
+                    get_module_path(&rel_dir)?.replace('/', "_")
+                );
+                self.process_file(file_path, parent, var_name, &Related::default(), 0)?;
+            } else {
+                nested.push((rel_dir, file_path.as_path(), var_name));
+            }
+        }
+        self.process_logical_modules(Path::new(""), &nested, 0)?;
+
+        Ok(())
+    }
+
+    /// The content of the file at `path`.
+    fn read(&self, path: &Path) -> std::io::Result<Cow<'a, [u8]>> {
+        read_source(self.entries, path)
+    }
+
+    /// The size of the file at `path`.
+    fn file_len(&self, path: &Path) -> std::io::Result<u64> {
+        match self.entries {
+            Some(_) => Ok(self.read(path)?.len() as u64),
+            None => Ok(fs::metadata(path)
+                .map_err(|err| with_path(err, path))?
+                .len()),
+        }
+    }
+
+    /// The files and directories directly in `dir`, each with its name and
+    /// whether it's a directory. Anything else, like a broken symlink, is
+    /// left out.
+    fn read_dir(&self, dir: &Path) -> std::io::Result<Vec<(std::ffi::OsString, PathBuf, bool)>> {
+        let Some(entries) = self.entries else {
+            let mut found = Vec::new();
+            for entry in fs::read_dir(dir)? {
+                let entry = entry?;
+                let path = entry.path();
+                if path.is_file() || path.is_dir() {
+                    let is_dir = path.is_dir();
+                    found.push((entry.file_name(), path, is_dir));
+                }
+            }
+            return Ok(found);
+        };
+
+        // Entries are sorted by component, so those under the same
+        // subdirectory are next to each other.
+        let mut found = Vec::new();
+        for path in entries.keys() {
+            let mut components = match path.strip_prefix(dir) {
+                Ok(rel_path) => rel_path.components(),
+                Err(_) => continue,
+            };
+            if let Some(name) = components.next() {
+                let is_dir = components.next().is_some();
+                found.push((name.as_os_str().to_owned(), dir.join(name), is_dir));
+            }
+        }
+        found.dedup();
+        Ok(found)
+    }
+    /// The URL hash digest of the file at `path`, then its ETag digest if
+    /// enabled. `content` replaces the file's own if given.
+    fn digests(&self, path: &Path, content: Option<&[u8]>) -> std::io::Result<Vec<Vec<u8>>> {
+        let normalized = if self.config.normalizes_line_endings(path) {
+            Some(match content {
+                Some(content) => crlf_to_lf(content),
+                None => crlf_to_lf(&self.read(path)?),
+            })
+        } else {
+            None
+        };
+        let content = normalized.as_deref().or(content);
+
+        let url_hasher = || match self.config.hasher {
+            Some(new) => Hasher::Custom(new()),
+            None => Hasher::new(self.config.hash_algorithm),
+        };
+        let hashers = std::iter::once(url_hasher())
+            .chain(self.config.etag.map(Hasher::new))
+            .collect();
+
+        let salt = &self.config.salt;
+        let buffer_size = self.config.hash_buffer_size.unwrap_or(HASH_BUFFER_SIZE);
+        let hash_file = |path: &Path, hashers| match self.entries {
+            Some(_) => calculate_hashes(&*self.read(path)?, salt, hashers, buffer_size),
+            None => File::open(path)
+                .and_then(|file| calculate_hashes(file, salt, hashers, buffer_size))
+                .map_err(|err| with_path(err, path)),
+        };
+        let mut digests = match content {
+            Some(content) => calculate_hashes(content, salt, hashers, buffer_size)?,
+            None => hash_file(path, hashers)?,
+        };
+
+        // The URL hash covers the file's own hash and each dependency's, in
+        // the order they were declared.
+        let mut dependencies = self.config.dependencies_of(path).peekable();
+        if dependencies.peek().is_some() {
+            let mut combined = url_hasher();
+            combined.update(&digests[0]);
+            for dependency in dependencies {
+                combined.update(&hash_file(dependency, vec![url_hasher()])?[0]);
+            }
+            digests[0] = combined.finalize();
+        }
+        Ok(digests)
+    }
+
+    /// The hashed URL of the file at `path`, given its URL hash `digest`.
+    fn url_path(&self, path: &Path, base_dir: &Path, digest: &[u8]) -> std::io::Result<String> {
+        let path = &*portable_path(path);
+        let hash = self.config.encode_hash(digest);
+
+        let named = self.config.logical_path_of(path).unwrap_or(path);
+        let file_stem = path_to_str(named.file_stem().unwrap_or_default(), path)?;
+        let extension = path_to_str(named.extension().unwrap_or_default(), path)?;
+        let served_extension = self.config.served_extension(extension);
+        let dot_extension = if served_extension.is_empty() {
+            String::new()
+        } else {
+            format!(".{served_extension}")
+        };
+
+        let rel_path = self.config.relative_path(path, base_dir);
+        let rel_dir = rel_path.parent().unwrap_or(Path::new(""));
+        let rel_dir_str = path_to_str(rel_dir.as_os_str(), path)?.replace('\\', "/");
+        let rel_path_str = path_to_str(rel_path.as_os_str(), path)?.replace('\\', "/");
+        if self.config.is_fixed_path(&rel_path_str) {
+            return Ok(self.config.with_url_form(format!("/{rel_path_str}")));
+        }
+        let unhashed = self.config.is_unhashed(&rel_path_str);
+
+        let prefix = self.config.url_prefix_for(path);
+        let url = match self.config.hash_placement {
+            _ if unhashed && rel_dir_str.is_empty() => {
+                format!("{prefix}{file_stem}{dot_extension}")
+            }
+            _ if unhashed => format!("{prefix}{rel_dir_str}/{file_stem}{dot_extension}"),
+            HashPlacement::FileName if rel_dir_str.is_empty() => {
+                format!("{prefix}{file_stem}-{hash}{dot_extension}")
+            }
+            HashPlacement::FileName => {
+                format!("{prefix}{rel_dir_str}/{file_stem}-{hash}{dot_extension}")
+            }
+            HashPlacement::Directory if rel_dir_str.is_empty() => {
+                format!("{prefix}{hash}/{file_stem}{dot_extension}")
+            }
+            HashPlacement::Directory => {
+                format!("{prefix}{hash}/{rel_dir_str}/{file_stem}{dot_extension}")
+            }
+        };
+        Ok(self.config.with_url_form(url))
+    }
+
+    /// Each enabled precompressed variant of `content`, a file with
+    /// `extension`.
+    fn variants(&self, content: &[u8], extension: &str) -> std::io::Result<Vec<Variant>> {
+        let compressible = self.config.compressible(extension);
+        let variants = [
+            (self.config.gzip_enabled(), "gzip", Encoding::Gzip, "gzip"),
+            (
+                self.config.brotli_enabled(),
+                "brotli",
+                Encoding::Brotli,
+                "br",
+            ),
+        ];
+        let mut compressed = Vec::new();
+        for (enabled, field, encoding, content_encoding) in variants {
+            if enabled {
+                let bytes = if compressible {
+                    compress(content, encoding)?
+                } else {
+                    None
+                };
+                compressed.push(Variant {
+                    field,
+                    content_encoding,
+                    compressed: bytes,
+                });
+            }
+        }
+        Ok(compressed)
+    }
+
+    /// Record the size of each of `variants` of the file at `rel_path` in the
+    /// manifest, with [`Config::manifest_sizes`].
+    fn insert_variant_sizes(&mut self, rel_path: &str, variants: &[Variant]) {
+        if !self.config.manifest_sizes {
+            return;
+        }
+        for variant in variants {
+            if let Some(compressed) = &variant.compressed {
+                let size = compressed.len() as u64;
+                self.manifest
+                    .insert_size(rel_path, variant.content_encoding, size);
+            }
+        }
+    }
+
+    /// `bytes`, an expression for a `&'static [u8]`, as written in a static
+    /// `indent_level` modules deep, which is a reference to a top-level
+    /// static holding it if [hoisting](Config::hoist_bytes).
+    fn embedded_bytes(&mut self, bytes: String, cfg_prefix: &str, indent_level: usize) -> String {
+        if !self.config.hoist_bytes {
+            return bytes;
+        }
+        let name = format!("BYTES_{}", self.hoisted_bytes.len());
+        self.hoisted_bytes
+            .push(format!("{cfg_prefix}static {name}: &[u8] = {bytes};"));
+        format!("{}{name}", "super::".repeat(indent_level))
+    }
+
+    fn is_out_path(&self, path: &Path) -> bool {
+        self.entries.is_none()
+            && self.out_path.as_ref().is_some_and(|out_path| {
+                out_path.file_name() == path.file_name()
+                    && fs::canonicalize(path).is_ok_and(|path| path == *out_path)
+            })
+    }
+
+    /// Generate the module for `dirs`, each the same directory under the
+    /// asset directory paired with it. There's more than one when
+    /// [overlaying](Config::overlay), with files in later ones replacing
+    /// those at the same path in earlier ones.
+    fn process_directory(
+        &mut self,
+        dirs: &[(PathBuf, PathBuf)],
+        indent_level: usize,
+    ) -> std::io::Result<()> {
+        let (dir, base_dir) = &dirs[0];
+        let rel_path = self.config.relative_path(dir, base_dir);
+        let dir_module_path = get_module_path(&rel_path)?;
+
+        let create_module = !rel_path.as_os_str().is_empty() && !self.config.flat;
+
+        if create_module {
+            let module_name =
+                sanitize_ident(path_to_str(rel_path.file_name().unwrap_or_default(), dir)?);
+
+            self.open_module(&module_name, self.config.cfg_attribute(dir), indent_level);
+
+            if !self.module_map.contains_key(&dir_module_path) {
+                self.module_map.insert(dir_module_path.clone(), Vec::new());
+            }
+        }
+
+        let first_digest = self.dir_digests.len();
+        let next_indent = if create_module {
+            indent_level + 1
+        } else {
+            indent_level
+        };
+
+        // Entries are processed in byte-wise order of their names, so the
+        // generated file doesn't depend on the order `read_dir` returns them.
+        let mut files = BTreeMap::new();
+        let mut dir_paths = BTreeMap::<_, Vec<_>>::new();
+        for (dir, base_dir) in dirs {
+            for (name, path, is_dir) in self.read_dir(dir)? {
+                if !self.config.include_hidden && name.as_encoded_bytes().starts_with(b".") {
+                    continue;
+                }
+
+                if !is_dir {
+                    if self.is_out_path(&path) || !self.config.modified_in_range(&path)? {
+                        continue;
+                    }
+                    files.insert(name, (path, base_dir));
+                } else {
+                    dir_paths
+                        .entry(name)
+                        .or_default()
+                        .push((path, base_dir.clone()));
+                }
+            }
+        }
+        let (files, base_dirs): (Vec<_>, Vec<_>) = files.into_values().unzip();
+
+        let mut idents = self.config.file_idents(&files)?;
+        if self.config.flat && !dir_module_path.is_empty() {
+            let prefix = dir_module_path.replace('/', "_");
+            for ident in &mut idents {
+                *ident = format!("{prefix}_{ident}");
+            }
+        }
+        let mut related = files.iter().map(|_| Related::default()).collect::<Vec<_>>();
+        if self.config.alternates {
+            let alternates = self.config.image_alternates(&files, &idents);
+            for (related, alternates) in related.iter_mut().zip(alternates) {
+                related.alternates = alternates;
+            }
+        }
+        if self.config.source_maps {
+            for (i, path) in files.iter().enumerate() {
+                let mut map_path = path.clone().into_os_string();
+                map_path.push(".map");
+                if let Some(j) = files.iter().position(|other| {
+                    *other.as_os_str() == map_path
+                        && self.config.cfg_attribute(other) == self.config.cfg_attribute(path)
+                }) {
+                    related[i].source_map = Some((idents[j].clone(), files[j].clone()));
+                }
+            }
+        }
+        for (((path, base_dir), var_name), related) in
+            files.iter().zip(base_dirs).zip(idents).zip(&related)
+        {
+            self.process_file(path, base_dir, var_name, related, next_indent)?;
+        }
+
+        let mut subdirs: Vec<(String, Vec<(PathBuf, PathBuf)>)> = Vec::new();
+        for paths in dir_paths.into_values() {
+            let path = &paths[0].0;
+            let module_name =
+                sanitize_ident(path_to_str(path.file_name().unwrap_or_default(), path)?);
+            if let Some((_, other)) = subdirs.iter().find(|(name, _)| *name == module_name) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "{} and {} would both be module `{}`",
+                        other[0].0.display(),
+                        path.display(),
+                        module_name
+                    ),
+                ));
+            }
+            subdirs.push((module_name, paths));
+        }
+
+        for (_, paths) in &subdirs {
+            self.process_directory(paths, next_indent)?;
+        }
+
+        if create_module && self.config.dir_hashes && !self.manifest_only {
+            // Paths are from this directory, so a subtree hashes the same
+            // wherever it is.
+            let mut hashed = Vec::new();
+            for (file_path, digest) in &self.dir_digests[first_digest..] {
+                let file_path = file_path.strip_prefix(&rel_path).unwrap_or(file_path);
+                let file_path = path_to_str(file_path.as_os_str(), dir)?.replace('\\', "/");
+                hashed.extend_from_slice(file_path.as_bytes());
+                hashed.push(0);
+                hashed.extend_from_slice(digest);
+            }
+            let hash = self
+                .config
+                .encode_hash(&self.digests(dir, Some(&hashed))?[0]);
+
+            let indent = "    ".repeat(indent_level);
+            self.output.push_str(&format!(
+                "\n{indent}    /// A hash of every file in this directory and those under it.\n{indent}    pub const DIR_HASH: &str = {hash:?};\n"
+            ));
+        }
+
+        if create_module && !self.manifest_only {
+            let indent = "    ".repeat(indent_level);
+            self.output.push_str(&format!("{}}}\n", indent));
+        }
+
+        Ok(())
+    }
+
+    /// Start the module `module_name`, `indent_level` modules deep, with the
+    /// `#[cfg]` attribute `cfg`.
+    fn open_module(&mut self, module_name: &str, cfg: Option<String>, indent_level: usize) {
+        if self.manifest_only {
+            return;
+        }
+        let indent = "    ".repeat(indent_level);
+        self.output.push('\n');
+        if indent_level == 0 {
+            self.output.push_str(&format!("{ALLOW_LINTS}\n"));
+        }
+        if let Some(cfg) = cfg {
+            self.output.push_str(&format!("{}{}\n", indent, cfg));
+        }
+        self.output
+            .push_str(&format!("{}pub mod {} {{\n", indent, module_name));
+        self.output.push_str(&format!(
+            "{}    use {};\n",
+            indent,
+            self.config
+                .static_file_path
+                .as_deref()
+                .unwrap_or("super::StaticFile")
+        ));
+    }
+
+    /// Generate the modules under `dir` for `files`, extra files with a
+    /// [logical path](Config::logical_path), given as the directory of that
+    /// path, the file and its identifier.
+    fn process_logical_modules(
+        &mut self,
+        dir: &Path,
+        files: &[(PathBuf, &Path, String)],
+        indent_level: usize,
+    ) -> std::io::Result<()> {
+        let mut subdirs = Vec::new();
+        for (rel_dir, _, _) in files {
+            if let Some(name) = rel_dir
+                .strip_prefix(dir)
+                .ok()
+                .and_then(|rel_dir| rel_dir.components().next())
+            {
+                subdirs.push(dir.join(name));
+            }
+        }
+        subdirs.sort();
+        subdirs.dedup();
+
+        for subdir in subdirs {
+            let module_path = get_module_path(&subdir)?;
+            if self.module_map.contains_key(&module_path) {
+                let file_path = files
+                    .iter()
+                    .find(|(rel_dir, _, _)| rel_dir.starts_with(&subdir))
+                    .map(|(_, file_path, _)| file_path.display());
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "{} would be in module `{}`, which is already generated",
+                        file_path.unwrap_or(subdir.display()),
+                        module_path
+                    ),
+                ));
+            }
+            self.module_map.insert(module_path, Vec::new());
+
+            let module_name = sanitize_ident(path_to_str(
+                subdir.file_name().unwrap_or_default(),
+                &subdir,
+            )?);
+            self.open_module(&module_name, None, indent_level);
+            for (rel_dir, file_path, var_name) in files {
+                if *rel_dir == subdir {
+                    let parent = file_path.parent().unwrap_or(Path::new(""));
+                    self.process_file(
+                        file_path,
+                        parent,
+                        var_name.clone(),
+                        &Related::default(),
+                        indent_level + 1,
+                    )?;
+                }
+            }
+            self.process_logical_modules(&subdir, files, indent_level + 1)?;
+            if !self.manifest_only {
+                self.output
+                    .push_str(&format!("{}}}\n", "    ".repeat(indent_level)));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn process_file(
+        &mut self,
+        path: &Path,
+        base_dir: &Path,
+        var_name: String,
+        related: &Related,
+        indent_level: usize,
+    ) -> std::io::Result<()> {
+        let full_path = match self.entries {
+            Some(_) => path.to_path_buf(),
+            None => fs::canonicalize(path).map_err(|err| with_path(err, path))?,
+        };
+        let file_name = path_to_str(full_path.as_os_str(), path)?;
+
+        if let Some(warn_size) = self.config.warn_size {
+            let size = self.file_len(path)?;
+            if size > warn_size {
+                self.report.large_files.push((path.to_path_buf(), size));
+            }
+        }
+
+        // Rewritten content replaces the file's own everywhere, including
+        // its hash, so the file's URL changes whenever its map's does.
+        let rewritten = match &related.source_map {
+            Some((_, map_path)) if self.config.rewrite_source_map_urls => {
+                let map_digests = self.digests(map_path, None)?;
+                let map_url = self.url_path(map_path, base_dir, &map_digests[0])?;
+                Some(rewrite_source_map_url(&self.read(path)?, &map_url))
+            }
+            _ => None,
+        };
+        let entries = self.entries;
+        let read_content = || match &rewritten {
+            Some(content) => Ok(content.clone()),
+            None => read_source(entries, path).map(Cow::into_owned),
+        };
+
+        let digests = self.digests(path, rewritten.as_deref())?;
+        let url_path = self.url_path(path, base_dir, &digests[0])?;
+
+        // Everything but reading the file goes by the name Windows would
+        // check it out as.
+        let disk_path = path;
+        let path = &*portable_path(disk_path);
+        if path != disk_path {
+            self.report.unportable_names.push(disk_path.to_path_buf());
+        }
+
+        let named = self.config.logical_path_of(path).unwrap_or(path);
+        let extension = path_to_str(named.extension().unwrap_or_default(), path)?;
+        let served_extension = self.config.served_extension(extension);
+
+        let rel_path = self.config.relative_path(path, base_dir);
+        let rel_dir = rel_path.parent().unwrap_or(Path::new(""));
+        let rel_dir_str = path_to_str(rel_dir.as_os_str(), path)?.replace('\\', "/");
+        let rel_path_str = path_to_str(rel_path.as_os_str(), path)?.replace('\\', "/");
+
+        let prefix = self.config.url_prefix_for(path);
+        let lowercase_path = format!("{prefix}{rel_path_str}").to_lowercase();
+        match self.lowercase_paths.get(&lowercase_path) {
+            Some((other_rel_path, other)) if *other_rel_path != rel_path_str => {
+                if self.config.deny_case_collisions {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!(
+                            "{} and {} differ only by case, so would collide on case-insensitive filesystems",
+                            other.display(),
+                            disk_path.display()
+                        ),
+                    ));
+                }
+                self.report
+                    .case_collisions
+                    .push((other.clone(), disk_path.to_path_buf()));
+            }
+            Some(_) => {}
+            None => {
+                self.lowercase_paths.insert(
+                    lowercase_path,
+                    (rel_path_str.clone(), disk_path.to_path_buf()),
+                );
+            }
+        }
+
+        self.manifest.insert(rel_path_str.clone(), url_path.clone());
+        self.hashes
+            .insert(rel_path_str.clone(), HashEncoding::Hex.encode(&digests[0]));
+        if self.config.dir_hashes {
+            self.dir_digests
+                .push((rel_path.to_path_buf(), digests[0].clone()));
+        }
+
+        let sniffed = if self.config.sniff_mime {
+            let mut start = Vec::with_capacity(16);
+            match self.entries {
+                Some(_) => start.extend(self.read(disk_path)?.iter().take(16)),
+                None => {
+                    File::open(disk_path)
+                        .and_then(|file| file.take(16).read_to_end(&mut start))
+                        .map_err(|err| with_path(err, disk_path))?;
+                }
+            }
+            sniff_mime_type(&start)
+        } else {
+            None
+        };
+        let mime_type = match self
+            .config
+            .mime_override(&rel_path_str, served_extension)
+            .or(sniffed)
+            .or_else(|| mime_type_from_extension(served_extension))
+        {
+            Some(mime_type) => mime_type,
+            None => match &self.config.mime_fallback {
+                MimeFallback::OctetStream => "application/octet-stream",
+                MimeFallback::Custom(mime_type) => mime_type,
+                MimeFallback::Error => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!("no MIME type known for {}", path.display()),
+                    ))
+                }
+            },
+        };
+
+        let module_path = if rel_dir_str.is_empty() || self.config.flat {
+            String::new()
+        } else {
+            get_module_path(rel_dir)?
+        };
+
+        let qualified_ident = format!("{module_path}/{var_name}");
+        if let Some(other) = self.idents.get(&qualified_ident) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "{} and {} would both be named `{}`",
+                    other.display(),
+                    path.display(),
+                    var_name
+                ),
+            ));
+        }
+        self.idents.insert(qualified_ident, path.to_path_buf());
+
+        // Statics that are already upper case, like `LICENSE`, would share a
+        // name with their const, so they go without. Upper casing can make
+        // two files' consts, or one's const and another's static, the same.
+        let const_name = var_name.to_uppercase();
+        let mut const_names = Vec::new();
+        if self.config.const_refs && const_name != var_name {
+            const_names.push(const_name.clone());
+        }
+        if self.config.routes {
+            const_names.push(format!("{const_name}_ROUTE"));
+        }
+        for name in const_names {
+            let qualified_name = format!("{module_path}/{name}");
+            if let Some(other) = self.idents.get(&qualified_name) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!(
+                        "{} and {} would both have a `{}`",
+                        other.display(),
+                        path.display(),
+                        name
+                    ),
+                ));
+            }
+            self.idents.insert(qualified_name, path.to_path_buf());
+        }
+
+        if self.manifest_only {
+            if self.config.manifest_sizes {
+                let size = match &rewritten {
+                    Some(content) => content.len() as u64,
+                    None => self.file_len(disk_path)?,
+                };
+                self.manifest.insert_size(&rel_path_str, "identity", size);
+                // Variants are only compressed for their sizes.
+                if self.config.gzip_enabled() || self.config.brotli_enabled() {
+                    let variants = self.variants(&read_content()?, extension)?;
+                    self.insert_variant_sizes(&rel_path_str, &variants);
+                }
+            }
+            return Ok(());
+        }
+
+        let meta_pairs = self
+            .config
+            .meta
+            .iter()
+            .filter(|(pattern, _, _)| glob::matches(pattern, &rel_path_str))
+            .map(|(_, key, value)| (key.clone(), value.clone()))
+            .collect::<Vec<_>>();
+        let meta = meta_pairs
+            .iter()
+            .map(|(key, value)| format!("({:?}, {:?})", key, value))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let cache_control = self
+            .config
+            .cache_control_for(&rel_path_str, served_extension);
+
+        let indent = "    ".repeat(indent_level);
+
+        let cfg = self.config.cfg_attribute(disk_path);
+        let mut cfg_line = cfg
+            .as_ref()
+            .map(|cfg| format!("{indent}{cfg}\n"))
+            .unwrap_or_default();
+        if indent_level == 0 {
+            cfg_line.insert_str(0, &format!("{ALLOW_LINTS}\n"));
+        }
+        let cfg_prefix = cfg.map(|cfg| format!("{cfg} ")).unwrap_or_default();
+
+        let doc_lines = match &self.config.doc_template {
+            Some(template) => render_template(
+                template,
+                &[
+                    ("path", file_name),
+                    ("url", &url_path),
+                    ("hash", &self.config.encode_hash(&digests[0])),
+                    ("mime", mime_type),
+                ],
+            )
+            .lines()
+            .map(str::to_string)
+            .collect(),
+            None => vec![format!("From {file_name:?}")],
+        };
+        let doc = doc_lines
+            .iter()
+            .map(|line| format!("{indent}/// {line}").trim_end().to_string() + "\n")
+            .collect::<String>();
+        let mut file_code = format!(
+            r#"
+{doc}{cfg_line}{indent}#[allow(non_upper_case_globals)]
+{indent}pub static {var_name}: StaticFile = StaticFile {{
+"#,
+        );
+        if self.config.has_field(Field::FileName) {
+            file_code.push_str(&format!("{indent}    file_name: {file_name:?},\n"));
+        }
+        file_code.push_str(&format!(
+            "{indent}    name: {url_path:?},\n{indent}    mime: {mime_type:?},\n"
+        ));
+        if self.config.has_field(Field::Meta) {
+            file_code.push_str(&format!("{indent}    meta: &[{meta}],\n"));
+        }
+        if self.config.has_field(Field::CacheControl) {
+            file_code.push_str(&format!("{indent}    cache_control: {cache_control:?},\n"));
+        }
+        let public_url = self.config.public_url(path, &url_path);
+        if self.config.public_url_prefix.is_some() {
+            file_code.push_str(&format!(
+                "{indent}    route: {url_path:?},\n{indent}    url: {public_url:?},\n"
+            ));
+        }
+        if self.config.content_hashes {
+            let hash = self.config.encode_hash(&digests[0]);
+            file_code.push_str(&format!("{indent}    hash: {hash:?},\n"));
+        }
+        if self.config.serve_root {
+            let source_path = path_within(disk_path, base_dir).unwrap_or(Cow::Borrowed(disk_path));
+            let source_path = path_to_str(source_path.as_os_str(), path)?.replace('\\', "/");
+            file_code.push_str(&format!("{indent}    rel_path: {source_path:?},\n"));
+        }
+        let etag = match self.config.etag {
+            Some(_) => format!("\"{}\"", self.config.etag_encoding().encode(&digests[1])),
+            None => String::new(),
+        };
+        if self.config.etag.is_some() {
+            file_code.push_str(&format!("{indent}    etag: {etag:?},\n"));
+        }
+        let download = self
+            .config
+            .downloads
+            .iter()
+            .any(|pattern| glob::matches(pattern, &rel_path_str));
+        let disposition = if download {
+            let file_stem = path_to_str(named.file_stem().unwrap_or_default(), path)?;
+            let file_name = if served_extension.is_empty() {
+                file_stem.to_string()
+            } else {
+                format!("{file_stem}.{served_extension}")
+            };
+            Some(content_disposition(&file_name))
+        } else {
+            None
+        };
+        if !self.config.downloads.is_empty() {
+            let disposition = match &disposition {
+                Some(disposition) => format!("Some({disposition:?})"),
+                None => "None".to_string(),
+            };
+            file_code.push_str(&format!("{indent}    disposition: {disposition},\n"));
+        }
+        let preload = self
+            .config
+            .preloads
+            .iter()
+            .any(|pattern| glob::matches(pattern, &rel_path_str));
+        if !self.config.preloads.is_empty() {
+            file_code.push_str(&format!("{indent}    preload: {preload},\n"));
+        }
+        if self.config.alternates {
+            let alternates = related
+                .alternates
+                .iter()
+                .map(|ident| format!("&{ident}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            file_code.push_str(&format!("{indent}    alternates: &[{alternates}],\n"));
+        }
+        if self.config.source_maps {
+            let source_map = match &related.source_map {
+                Some((ident, _)) => format!("Some(&{ident})"),
+                None => "None".to_string(),
+            };
+            file_code.push_str(&format!("{indent}    source_map: {source_map},\n"));
+        }
+        if self.config.compress_embedded_enabled() {
+            let compressed = brotli_compress(&read_content()?)?;
+            let compressed =
+                self.embedded_bytes(byte_string(&compressed), &cfg_prefix, indent_level);
+            file_code.push_str(&format!(
+                "{indent}    compressed_content: {compressed},\n{indent}    content_cache: std::sync::OnceLock::new(),\n",
+            ));
+        } else if let Some(content) = rewritten.as_deref().filter(|_| self.config.embed) {
+            let content = self.embedded_bytes(byte_string(content), &cfg_prefix, indent_level);
+            file_code.push_str(&format!("{indent}    content: {content},\n"));
+        } else if self.config.embed && self.entries.is_some() {
+            let content = byte_string(&self.read(disk_path)?);
+            let content = self.embedded_bytes(content, &cfg_prefix, indent_level);
+            file_code.push_str(&format!("{indent}    content: {content},\n"));
+        } else if self.config.embed {
+            let relative = self
+                .out_dir
+                .as_ref()
+                .filter(|_| self.config.relative_includes)
+                .and_then(|out_dir| relative_to(&full_path, out_dir));
+            let include_path = match &relative {
+                Some(relative) => path_to_str(relative.as_os_str(), path)?.replace('\\', "/"),
+                None => file_name.to_string(),
+            };
+            let content = self.embedded_bytes(
+                format!("include_bytes!({include_path:?})"),
+                &cfg_prefix,
+                indent_level,
+            );
+            file_code.push_str(&format!("{indent}    content: {content},\n"));
+        }
+        if self.config.manifest_sizes {
+            let size = match &rewritten {
+                Some(content) => content.len() as u64,
+                None => self.file_len(disk_path)?,
+            };
+            self.manifest.insert_size(&rel_path_str, "identity", size);
+        }
+        if self.config.gzip_enabled() || self.config.brotli_enabled() {
+            let variants = self.variants(&read_content()?, extension)?;
+            self.insert_variant_sizes(&rel_path_str, &variants);
+            for variant in variants {
+                let value = match &variant.compressed {
+                    Some(compressed) => {
+                        let bytes = byte_string(compressed);
+                        format!(
+                            "Some({})",
+                            self.embedded_bytes(bytes, &cfg_prefix, indent_level)
+                        )
+                    }
+                    None => "None".to_string(),
+                };
+                let field = variant.field;
+                file_code.push_str(&format!("{indent}    {field}: {value},\n"));
+            }
+        }
+        file_code.push_str(&format!("{indent}}};\n"));
+
+        self.output.push_str(&file_code);
+        self.records.push(StaticRecord {
+            module_path: module_path.clone(),
+            ident: var_name.clone(),
+            doc: doc_lines,
+            file_name: file_name.to_string(),
+            name: url_path.clone(),
+            mime: mime_type.to_string(),
+            meta: meta_pairs,
+            cache_control: cache_control.to_string(),
+            hash: self.config.encode_hash(&digests[0]),
+            etag,
+            disposition,
+            preload,
+        });
+
+        if self.config.const_refs && const_name != var_name {
+            self.output.push_str(&format!(
+                "{cfg_line}{indent}pub const {const_name}: &StaticFile = &{var_name};\n"
+            ));
+        }
+
+        if self.config.routes {
+            self.output.push_str(&format!(
+                "{cfg_line}{indent}pub const {const_name}_ROUTE: &str = {url_path:?};\n"
+            ));
+        }
+
+        let reference = if module_path.is_empty() {
+            format!("&{}", var_name)
+        } else {
+            let module_parts: Vec<&str> = module_path.split('/').collect();
+            if module_parts.len() > 1 {
+                let mut parts = Vec::new();
+                for part in &module_parts {
+                    if !part.is_empty() {
+                        parts.push(*part);
+                    }
+                }
+                format!("&{}::{}", parts.join("::"), var_name)
+            } else {
+                format!("&{}::{}", module_path, var_name)
+            }
+        };
+
+        if self.fallback_path.as_ref() == Some(&full_path) {
+            self.fallback = Some(reference.clone());
+        }
+
+        if self.config.index_files && rel_path.file_name() == Some("index.html".as_ref()) {
+            let prefix = self.config.url_prefix_for(path);
+            let dir_url = self.config.with_url_form(if rel_dir_str.is_empty() {
+                prefix
+            } else {
+                format!("{prefix}{rel_dir_str}/")
+            });
+            self.indexes
+                .push(format!("{}({:?}, {})", cfg_prefix, dir_url, reference));
+        }
+
+        if self.config.url_macro {
+            self.macro_arms
+                .push(format!("({rel_path_str:?}) => {{ {public_url:?} }};"));
+        }
+        if self.config.resolve {
+            self.logical_names.push(format!(
+                "{cfg_prefix}{rel_path_str:?} => Some({public_url:?}),"
+            ));
+        }
+        if self.config.match_lookup || self.config.phf_lookup_enabled() {
+            let key = match self.config.url_form {
+                UrlForm::Absolute => url_path.as_str(),
+                _ => url_path.trim_start_matches("./").trim_start_matches('/'),
+            };
+            let file = format!("&super::{}", reference.trim_start_matches('&'));
+            if self.config.match_lookup {
+                self.lookups
+                    .push(format!("{cfg_prefix}{key:?} => Some({file}),"));
+            } else {
+                self.phf_entries.push((key.to_string(), file));
+            }
+        }
+        self.static_files
+            .push(format!("{}{}", cfg_prefix, reference));
+
+        if self.config.reexport && !module_path.is_empty() {
+            let top_level = format!("/{var_name}");
+            if let Some(other) = self.idents.get(&top_level) {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!(
+                        "{} and {} would both be re-exported as `{}`",
+                        other.display(),
+                        path.display(),
+                        var_name
+                    ),
+                ));
+            }
+            self.idents.insert(top_level, path.to_path_buf());
+            self.reexports
+                .push(format!("{}pub use {};", cfg_prefix, &reference[1..]));
+        }
+
+        if self.config.asset_enum {
+            let base = module_path
+                .split('/')
+                .chain([var_name.as_str()])
+                .map(camel_case)
+                .collect::<String>();
+            let base = match base.chars().next() {
+                None => "Unnamed".to_string(),
+                Some(c) if c.is_ascii_digit() => format!("_{base}"),
+                Some(_) if base == "Self" => "Self_".to_string(),
+                Some(_) => base,
+            };
+            let mut variant = base.clone();
+            for suffix in 2.. {
+                if !self
+                    .asset_variants
+                    .iter()
+                    .any(|(other, _, _)| *other == variant)
+                {
+                    break;
+                }
+                variant = format!("{base}{suffix}");
+            }
+            self.asset_variants
+                .push((variant, cfg_prefix.clone(), reference.clone()));
+        }
+
+        if let Some(category) = category(mime_type).filter(|_| self.config.categories) {
+            let uses = self.categories.entry(category).or_default();
+            let mut name = var_name.clone();
+            for suffix in 2.. {
+                if !uses.iter().any(|(other, _)| *other == name) {
+                    break;
+                }
+                name = format!("{var_name}_{suffix}");
+            }
+            let rename = if name == var_name {
+                String::new()
+            } else {
+                format!(" as {name}")
+            };
+            let reexport = format!("{cfg_prefix}pub use super::{}{rename};", &reference[1..]);
+            uses.push((name, reexport));
+        }
+
+        if let Some(vars) = self.module_map.get_mut(&module_path) {
+            vars.push(var_name);
+        }
+
+        self.flush()
+    }
+}
+
+/// The `phf::Map` expression for `entries`, with [`Config::phf_lookup`].
+/// The first entry for a key wins, as in a `match`.
+fn phf_map(config: &Config, entries: &[(String, String)]) -> Option<String> {
+    #[cfg(feature = "phf")]
+    if config.phf_lookup {
+        let mut keys = std::collections::HashSet::new();
+        let mut map = phf_codegen::Map::new();
+        for (key, value) in entries {
+            if keys.insert(key.as_str()) {
+                map.entry(key.as_str(), value.as_str());
+            }
+        }
+        return Some(map.build().to_string());
+    }
+    #[cfg(not(feature = "phf"))]
+    let _ = (config, entries);
+    None
+}
+
+/// `asset_dirs` without those inside another of them or listed again, as
+/// their files are already walked as part of the first.
+fn distinct_asset_dirs(asset_dirs: &[PathBuf]) -> Vec<&PathBuf> {
+    asset_dirs
+        .iter()
+        .enumerate()
+        .filter(|&(i, dir)| {
+            !asset_dirs.iter().enumerate().any(|(j, other)| {
+                j != i
+                    && path_within(dir, other)
+                        .is_some_and(|rel_path| j < i || !rel_path.as_os_str().is_empty())
+            })
+        })
+        .map(|(_, dir)| dir)
+        .collect()
+}
+
+/// The URL `prefix` with exactly one leading and trailing slash, eg.
+/// `/static/` for `static`.
+fn slashed_prefix(prefix: &str) -> String {
+    let prefix = prefix.trim_matches('/');
+    if prefix.is_empty() {
+        "/".to_string()
+    } else {
+        format!("/{prefix}/")
+    }
+}
+
+/// `path` relative to `dir`, if it's inside it. Paths that don't share a
+/// prefix as given, eg. because only one goes through a symlink or `..`, are
+/// compared canonicalized.
+fn path_within<'p>(path: &'p Path, dir: &Path) -> Option<Cow<'p, Path>> {
+    if let Ok(rel_path) = path.strip_prefix(dir) {
+        return Some(Cow::Borrowed(rel_path));
+    }
+    let path = fs::canonicalize(path).ok()?;
+    let rel_path = path.strip_prefix(fs::canonicalize(dir).ok()?).ok()?;
+    Some(Cow::Owned(rel_path.to_path_buf()))
+}
+
+/// The identifier for the static generated for `path`.
+fn file_ident(path: &Path, strategy: IdentStrategy) -> std::io::Result<String> {
+    let path = &*portable_path(path);
+    let file_name = path_to_str(path.file_name().unwrap_or_default(), path)?;
+    let name = match strategy {
+        IdentStrategy::FileName => file_name.to_string(),
+        IdentStrategy::FileStem => {
+            path_to_str(path.file_stem().unwrap_or_default(), path)?.to_string()
+        }
+        IdentStrategy::Custom(ident) => ident(file_name),
+    };
+    Ok(sanitize_ident(&name))
+}
+
+/// `path` without the dots and spaces that end its file name, which Windows
+/// strips when creating files, so every platform agrees on its name.
+fn portable_path(path: &Path) -> Cow<'_, Path> {
+    let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+        return Cow::Borrowed(path);
+    };
+    let trimmed = file_name.trim_end_matches(['.', ' ']);
+
+    if trimmed.len() == file_name.len() || trimmed.is_empty() {
+        Cow::Borrowed(path)
+    } else {
+        Cow::Owned(path.with_file_name(trimmed))
+    }
+}
+
+/// Turn `name` into a valid identifier by replacing each run of other
+/// characters with a single `_`, eg. `a_b_js` for `a + b.js`, prefixing names
+/// that start with a digit and suffixing keywords and the names of items
+/// generated alongside the statics.
+fn sanitize_ident(name: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "match", "mod",
+        "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+        "trait", "true", "type", "unsafe", "use", "where", "while", "abstract", "become", "box",
+        "do", "final", "macro", "override", "priv", "try", "typeof", "unsized", "virtual", "yield",
+        "_",
+    ];
+    const GENERATED: &[&str] = &[
+        "StaticFile",
+        "StaticKind",
+        "STATICS",
+        "INDEXES",
+        "BASE_URL",
+        "SERVE_ROOT",
+        "GENERATED_AT",
+        "DIR_HASH",
+        "Asset",
+        "static_file_impls",
+    ];
+
+    let mut ident = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            ident.push(c);
+        } else if !ident.ends_with('_') {
+            ident.push('_');
+        }
+    }
+
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    if KEYWORDS.contains(&ident.as_str()) || GENERATED.contains(&ident.as_str()) {
+        ident.push('_');
+    }
+
+    ident
+}
+
+/// `ident` in upper camel case, eg. `ScriptJs` for `script_js`.
+fn camel_case(ident: &str) -> String {
+    ident
+        .split('_')
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_ascii_uppercase())
+                .into_iter()
+                .chain(chars)
+        })
+        .collect()
+}
+
+/// The `/` separated module path for the directory at `path`, relative to
+/// its asset directory, eg. `images/_2x` for `images/2x`.
+fn get_module_path(path: &Path) -> std::io::Result<String> {
+    Ok(path_to_str(path.as_os_str(), path)?
+        .replace('\\', "/")
+        .split('/')
+        .filter(|part| !part.is_empty())
+        .map(sanitize_ident)
+        .collect::<Vec<_>>()
+        .join("/"))
+}
+
+/// Borrow `part` of `path` as UTF-8, failing with an error naming `path`.
+fn path_to_str<'a>(part: &'a std::ffi::OsStr, path: &Path) -> std::io::Result<&'a str> {
+    part.to_str().ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("path is not valid UTF-8: {}", path.display()),
+        )
+    })
+}
+
+/// `template` with each `{name}` among `values` replaced by its value, in
+/// one pass so values aren't themselves expanded.
+fn render_template(template: &str, values: &[(&str, &str)]) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let value = values.iter().find_map(|(name, value)| {
+            let placeholder = rest.strip_prefix('{')?.strip_prefix(name)?;
+            Some((*value, placeholder.strip_prefix('}')?))
+        });
+        match value {
+            Some((value, after)) => {
+                rendered.push_str(value);
+                rest = after;
+            }
+            None => {
+                rendered.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// `path` relative to the directory `dir`, both canonical, or `None` if they
+/// have different roots, eg. Windows drives.
+fn relative_to(path: &Path, dir: &Path) -> Option<PathBuf> {
+    let mut path_components = path.components().peekable();
+    let mut dir_components = dir.components().peekable();
+    if path_components.next() != dir_components.next() {
+        return None;
+    }
+    while path_components.peek().is_some() && path_components.peek() == dir_components.peek() {
+        path_components.next();
+        dir_components.next();
+    }
+
+    let mut relative = dir_components.map(|_| "..").collect::<PathBuf>();
+    relative.extend(path_components);
+    Some(relative)
+}
+
+/// The contents of the file at `path`, with the path in any error.
+fn read_file(path: &Path) -> std::io::Result<Vec<u8>> {
+    fs::read(path).map_err(|err| with_path(err, path))
+}
+
+/// The content of the file at `path`, from `entries` if reading an archive.
+fn read_source<'e>(
+    entries: Option<&'e BTreeMap<PathBuf, Vec<u8>>>,
+    path: &Path,
+) -> std::io::Result<Cow<'e, [u8]>> {
+    match entries {
+        Some(entries) => entries
+            .get(path)
+            .map(|content| Cow::Borrowed(content.as_slice()))
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("{} is not in the archive", path.display()),
+                )
+            }),
+        None => read_file(path).map(Cow::Owned),
+    }
+}
+
+/// `err` with its message prefixed by the `path` it happened on.
+fn with_path(err: std::io::Error, path: &Path) -> std::io::Error {
+    std::io::Error::new(err.kind(), format!("{}: {}", path.display(), err))
+}
+
+/// How many bytes of a file are hashed at a time by default.
+const HASH_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Digest `file`, prefixed with `salt`, with every one of `hashers` while
+/// reading it only once, `buffer_size` bytes at a time.
+/// Reads interrupted by a signal, as happen on network filesystems, are
+/// retried.
+fn calculate_hashes(
+    mut file: impl Read,
+    salt: &str,
+    mut hashers: Vec<Hasher>,
+    buffer_size: usize,
+) -> std::io::Result<Vec<Vec<u8>>> {
+    let mut buffer = vec![0; buffer_size.max(1)];
+
+    for hasher in &mut hashers {
+        hasher.update(salt.as_bytes());
+    }
+
+    loop {
+        let read = match file.read(&mut buffer) {
+            Ok(read) => read,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        if read == 0 {
+            break;
+        }
+        for hasher in &mut hashers {
+            hasher.update(&buffer[..read]);
+        }
+    }
+
+    Ok(hashers.into_iter().map(Hasher::finalize).collect())
+}
+
+/// `content` with every CRLF replaced by LF.
+fn crlf_to_lf(content: &[u8]) -> Vec<u8> {
+    let mut normalized = Vec::with_capacity(content.len());
+    for (i, &byte) in content.iter().enumerate() {
+        if byte != b'\r' || content.get(i + 1) != Some(&b'\n') {
+            normalized.push(byte);
+        }
+    }
+    normalized
+}
+
+/// `content` with its last `sourceMappingURL` comment pointing at `url`, or
+/// unchanged if it has none.
+fn rewrite_source_map_url(content: &[u8], url: &str) -> Vec<u8> {
+    const MARKER: &[u8] = b"sourceMappingURL=";
+
+    let Some(start) = content
+        .windows(MARKER.len())
+        .rposition(|window| window == MARKER)
+        .map(|position| position + MARKER.len())
+    else {
+        return content.to_vec();
+    };
+    let end = content[start..]
+        .iter()
+        .position(|byte| byte.is_ascii_whitespace() || *byte == b'*')
+        .map_or(content.len(), |length| start + length);
+
+    [&content[..start], url.as_bytes(), &content[end..]].concat()
+}
+
+/// A precompressed content encoding.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Gzip,
+    Brotli,
+}
+
+/// Whether files with `extension` are worth compressing, ie. they aren't an
+/// already compressed format.
+fn compressible(extension: &str) -> bool {
+    !matches!(
+        extension.to_ascii_lowercase().as_str(),
+        "png"
+            | "jpg"
+            | "jpeg"
+            | "gif"
+            | "webp"
+            | "avif"
+            | "ico"
+            | "woff"
+            | "woff2"
+            | "zip"
+            | "gz"
+            | "br"
+            | "zst"
+            | "mp3"
+            | "mp4"
+            | "ogg"
+            | "webm"
+            | "pdf"
+    )
+}
+
+/// `content` compressed with `encoding`, or `None` if that isn't smaller or
+/// the encoding's feature is disabled.
+#[allow(unused_variables)]
+fn compress(content: &[u8], encoding: Encoding) -> std::io::Result<Option<Vec<u8>>> {
+    let smaller = |compressed: Vec<u8>| Some(compressed).filter(|c| c.len() < content.len());
+
+    match encoding {
+        #[cfg(feature = "gzip")]
+        Encoding::Gzip => {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+            encoder.write_all(content)?;
+            Ok(smaller(encoder.finish()?))
+        }
+        #[cfg(feature = "brotli")]
+        Encoding::Brotli => Ok(smaller(brotli_compress(content)?)),
+        #[allow(unreachable_patterns)]
+        _ => Ok(None),
+    }
+}
+
+/// `content` brotli-compressed at the highest quality.
+#[cfg(feature = "brotli")]
+fn brotli_compress(content: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut compressed = Vec::new();
+    let params = brotli::enc::BrotliEncoderParams {
+        quality: 11,
+        ..Default::default()
+    };
+    brotli::BrotliCompress(&mut &content[..], &mut compressed, &params)?;
+    Ok(compressed)
+}
+
+/// Stands in for the real `brotli_compress` so callers needn't be
+/// feature-gated. Never called, as `compress_embedded` needs `brotli`.
+#[cfg(not(feature = "brotli"))]
+fn brotli_compress(_content: &[u8]) -> std::io::Result<Vec<u8>> {
+    unreachable!("compress_embedded requires the brotli feature")
+}
+
+/// `bytes` as a Rust byte string literal, eg. `b"\x1f\x8b"`.
+fn byte_string(bytes: &[u8]) -> String {
+    let mut literal = String::with_capacity(bytes.len() * 4 + 3);
+    literal.push_str("b\"");
+    for &byte in bytes {
+        match byte {
+            b'"' => literal.push_str("\\\""),
+            b'\\' => literal.push_str("\\\\"),
+            b' '..=b'~' => literal.push(byte as char),
+            _ => literal.push_str(&format!("\\x{:02x}", byte)),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// The current UTC time as an RFC 3339 timestamp, eg. `2025-01-31T09:30:00Z`.
+fn rfc3339_now() -> String {
+    let seconds = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+
+    let days = (seconds / 86_400) as i64;
+    let time = seconds % 86_400;
+
+    // Civil date from days since the epoch, per Howard Hinnant's algorithm.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60
+    )
+}
+
+/// Whether files of `mime` type are text, for
+/// [`Config::normalize_line_endings`].
+fn is_text_mime(mime: &str) -> bool {
+    let essence = mime.split(';').next().unwrap_or_default().trim();
+    essence.starts_with("text/")
+        || essence.ends_with("+xml")
+        || essence.ends_with("+json")
+        || matches!(
+            essence,
+            "application/javascript" | "application/json" | "application/xml"
+        )
+}
+
+/// The category module for statics of `mime` type, classified like
+/// `StaticFile::kind`.
+fn category(mime: &str) -> Option<&'static str> {
+    match mime {
+        "text/css" => Some("styles"),
+        "application/javascript" | "text/javascript" => Some("scripts"),
+        mime if mime.starts_with("image/") => Some("images"),
+        _ => None,
+    }
+}
+
+fn mime_type_from_extension(extension: &str) -> Option<&'static str> {
+    match extension.to_ascii_lowercase().as_str() {
+        "svg" => Some("image/svg+xml"),
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "webp" => Some("image/webp"),
+        "avif" => Some("image/avif"),
+        "css" => Some("text/css"),
+        "html" | "htm" => Some("text/html"),
+        "js" => Some("application/javascript"),
+        "wasm" => Some("application/wasm"),
+        _ => None,
+    }
+}
+
+/// The `Content-Disposition` for downloading a file as `file_name`. Names
+/// that aren't plain ASCII get an ASCII fallback along with the exact name
+/// as an RFC 5987 `filename*`.
+fn content_disposition(file_name: &str) -> String {
+    let fallback = file_name
+        .chars()
+        .map(|c| {
+            if (c.is_ascii_graphic() && c != '"' && c != '\\') || c == ' ' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if fallback == file_name {
+        return format!("attachment; filename=\"{file_name}\"");
+    }
+
+    let encoded = file_name
+        .bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&byte) {
+                char::from(byte).to_string()
+            } else {
+                format!("%{byte:02X}")
+            }
+        })
+        .collect::<String>();
+    format!("attachment; filename=\"{fallback}\"; filename*=UTF-8''{encoded}")
+}
+
+/// The MIME type of a file starting with `start`, by its signature.
+fn sniff_mime_type(start: &[u8]) -> Option<&'static str> {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"\0asm", "application/wasm"),
+        (b"wOFF", "font/woff"),
+        (b"wOF2", "font/woff2"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+    ];
+
+    if let Some((_, mime)) = SIGNATURES
+        .iter()
+        .find(|(signature, _)| start.starts_with(signature))
+    {
+        return Some(mime);
+    }
+    match (start.get(..4), start.get(8..12)) {
+        (Some(b"RIFF"), Some(b"WEBP")) => Some("image/webp"),
+        _ => match start.get(4..12) {
+            Some(b"ftypavif" | b"ftypavis") => Some("image/avif"),
+            _ => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::Digest;
+    use std::fs;
+    use std::io::Write;
+    use std::process::Command;
+    use tempfile::{tempdir, NamedTempFile};
+
+    fn create_temp_file(content: &[u8], extension: &str) -> (NamedTempFile, PathBuf) {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(content).unwrap();
+        let path = file.path().to_path_buf();
+
+        let new_path = path.with_extension(extension);
+        fs::rename(&path, &new_path).unwrap();
+
+        (file, new_path)
+    }
+
+    /// Generates a statics file with `config` from an asset directory laid
+    /// out as `files`, paths relative to it, and checks that it compiles.
+    /// Paths ending in `/` create empty directories.
+    fn assert_compiles(config: &Config, files: &[&str]) {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        for file in files {
+            let path = asset_dir.join(file);
+            if file.ends_with('/') {
+                fs::create_dir_all(&path).unwrap();
+            } else {
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, file).unwrap();
+            }
+        }
+
+        config.codegen(&out_path, &[asset_dir], &[]).unwrap();
+        run_generated(&out_path, "");
+    }
+
+    /// Compiles the generated file at `generated` together with `main` as the
+    /// body of `fn main` and runs the result, panicking on any failure.
+    fn run_generated(generated: &Path, main: &str) {
+        run_program(&format!(
+            "include!({:?});\n\nfn main() {{\n{}\n}}\n",
+            generated, main
+        ));
+    }
+
+    /// Compiles `source` as a binary crate and runs it, panicking on any
+    /// failure.
+    fn run_program(source: &str) {
+        run_program_with_externs(source, &[]);
+    }
+
+    /// Compiles `source` as a binary crate, panicking unless it fails with
+    /// an error containing `message`.
+    fn assert_compile_error(source: &str, message: &str) {
+        let dir = tempdir().unwrap();
+        let src_path = dir.path().join("main.rs");
+        fs::write(&src_path, source).unwrap();
+
+        let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+        let output = Command::new(rustc)
+            .args(["--edition", "2021", "--emit", "metadata", "--out-dir"])
+            .arg(dir.path())
+            .arg(&src_path)
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!output.status.success(), "compiled unexpectedly");
+        assert!(stderr.contains(message), "{}", stderr);
+    }
+
+    /// Compiles the generated file at `generated` into a `#![no_std]` library,
+    /// panicking on any failure.
+    fn assert_compiles_no_std(generated: &Path) {
+        let dir = tempdir().unwrap();
+        let src_path = dir.path().join("lib.rs");
+        fs::write(
+            &src_path,
+            format!("#![no_std]\n\ninclude!({generated:?});\n"),
+        )
+        .unwrap();
+
+        let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+        let output = Command::new(rustc)
+            .args(["--edition", "2021", "--crate-type", "rlib", "--out-dir"])
+            .arg(dir.path())
+            .arg(&src_path)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    /// Like [`run_program`], linking the given crates from this crate's own
+    /// dependencies.
+    fn run_program_with_externs(source: &str, externs: &[&str]) {
+        let dir = tempdir().unwrap();
+        let src_path = dir.path().join("main.rs");
+        let bin_path = dir.path().join("main");
+
+        fs::write(&src_path, source).unwrap();
+
+        let deps_dir = std::env::current_exe()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .to_path_buf();
+        let mut extern_args = vec!["-L".into(), format!("dependency={}", deps_dir.display())];
+        for extern_name in externs {
+            // `name@1` picks a major version among several in the deps, by
+            // the sources listed in each one's dep-info file.
+            let (name, version) = match extern_name.split_once('@') {
+                Some((name, version)) => (name, Some(version)),
+                None => (*extern_name, None),
+            };
+            let prefix = format!("lib{name}-");
+            let rlib = fs::read_dir(&deps_dir)
+                .unwrap()
+                .map(|entry| entry.unwrap().path())
+                .filter(|path| {
+                    let file_name = path.file_name().unwrap().to_string_lossy();
+                    file_name.starts_with(&prefix) && file_name.ends_with(".rlib")
+                })
+                .filter(|path| {
+                    version.is_none_or(|version| {
+                        let file_name = path.file_stem().unwrap().to_string_lossy();
+                        let dep_info = deps_dir.join(format!("{}.d", &file_name[3..]));
+                        fs::read_to_string(dep_info)
+                            .is_ok_and(|sources| sources.contains(&format!("/{name}-{version}.")))
+                    })
+                })
+                .max_by_key(|path| fs::metadata(path).unwrap().modified().unwrap())
+                .unwrap_or_else(|| panic!("no rlib for {name} in {}", deps_dir.display()));
+            extern_args.push("--extern".into());
+            extern_args.push(format!("{name}={}", rlib.display()));
+        }
+
+        let rustc = std::env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+        let output = Command::new(rustc)
+            .args(["--edition", "2021", "-o"])
+            .arg(&bin_path)
+            .arg(&src_path)
+            .args(&extern_args)
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let output = Command::new(&bin_path).output().unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    #[test]
+    fn test_basic_file_processing() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let test_content = b"test content";
+        let (_file, file_path) = create_temp_file(test_content, "js");
+
+        codegen(&out_path, &[], std::slice::from_ref(&file_path)).unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+
+        assert!(generated.contains("pub struct StaticFile"));
+        assert!(generated.contains(&format!("file_name: \"{}\"", file_path.to_str().unwrap())));
+        assert!(generated.contains("pub static STATICS: &[&StaticFile]"));
+
+        let hash = format!("{:x}", md5::compute(test_content));
+        assert!(generated.contains(&hash));
+    }
+
+    #[test]
+    fn test_directory_structure() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let nested_dir = dir.path().join("vendor");
+        fs::create_dir(&nested_dir).unwrap();
+
+        let root_content = b"root file";
+        let nested_content = b"nested file";
+
+        let (_root_file, root_path) = create_temp_file(root_content, "css");
+        fs::rename(&root_path, dir.path().join("root.css")).unwrap();
+
+        let nested_path = nested_dir.join("script.js");
+        let mut nested_file = File::create(&nested_path).unwrap();
+        nested_file.write_all(nested_content).unwrap();
+
+        codegen(&out_path, &[dir.path().to_path_buf()], &[]).unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+
+        assert!(generated.contains("pub mod vendor {"));
+        assert!(generated.contains("pub static root_css"));
+        assert!(generated.contains("pub static script_js"));
+
+        assert!(generated.contains("\"/static/root-"));
+        assert!(generated.contains("\"/static/vendor/script-"));
+
+        assert!(generated.contains("&root_css"));
+        assert!(generated.contains("&vendor::script_js"));
+    }
+
+    #[test]
+    fn test_mime_type_detection() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let extensions = vec!["svg", "png", "jpg", "css", "js", "wasm", "webp", "unknown"];
+        let mut file_paths = Vec::new();
+
+        for ext in extensions {
+            let (_file, path) = create_temp_file(format!("content for {}", ext).as_bytes(), ext);
+            file_paths.push(path);
+        }
+
+        codegen(&out_path, &[], &file_paths).unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+
+        assert!(generated.contains("mime: \"image/svg+xml\""));
+        assert!(generated.contains("mime: \"image/png\""));
+        assert!(generated.contains("mime: \"image/jpeg\""));
+        assert!(generated.contains("mime: \"text/css\""));
+        assert!(generated.contains("mime: \"application/javascript\""));
+        assert!(generated.contains("mime: \"application/wasm\""));
+        assert!(generated.contains("mime: \"image/webp\""));
+        assert!(generated.contains("mime: \"application/octet-stream\""));
+    }
+
+    #[test]
+    fn test_mime_type_case_insensitive() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        fs::write(asset_dir.join("IMAGE.PNG"), b"image").unwrap();
+        fs::write(asset_dir.join("style.CSS"), b"style").unwrap();
+        fs::write(asset_dir.join("app.Js"), b"app").unwrap();
+
+        codegen(&out_path, &[asset_dir], &[]).unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains("mime: \"image/png\""));
+        assert!(generated.contains("mime: \"text/css\""));
+        assert!(generated.contains("mime: \"application/javascript\""));
+        assert!(!generated.contains("application/octet-stream"));
+    }
+
+    #[test]
+    fn test_mime_overrides() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("fonts")).unwrap();
+        fs::write(asset_dir.join("fonts/inter.bin"), b"inter").unwrap();
+        fs::write(asset_dir.join("fonts/mono.txt"), b"mono").unwrap();
+        fs::write(asset_dir.join("notes.txt"), b"notes").unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+
+        Config::new()
+            .mime_for_extension("TXT", "text/plain")
+            .mime_for_extension("js", "text/javascript")
+            .mime_for_glob("fonts/**", "font/woff2")
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+
+        run_generated(
+            &out_path,
+            r#"
+    assert_eq!(fonts::inter_bin.mime, "font/woff2");
+    assert_eq!(fonts::mono_txt.mime, "font/woff2");
+    assert_eq!(notes_txt.mime, "text/plain");
+    assert_eq!(app_js.mime, "text/javascript");
+"#,
+        );
+    }
+
+    #[test]
+    fn test_cache_control() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        fs::write(asset_dir.join("main.css"), b"main").unwrap();
+        fs::write(asset_dir.join("index.html"), b"index").unwrap();
+        fs::write(asset_dir.join("data.json"), b"{}").unwrap();
+        fs::write(asset_dir.join("about.html"), b"about").unwrap();
+
+        Config::new()
+            .cache_control("*.json", "no-store")
+            .cache_control("about.html", "public, max-age=60")
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+
+        run_generated(
+            &out_path,
+            r#"
+    assert_eq!(main_css.cache_control, "public, max-age=31536000, immutable");
+    assert_eq!(index_html.cache_control, "no-cache");
+    assert_eq!(data_json.cache_control, "no-store");
+    assert_eq!(about_html.cache_control, "public, max-age=60");
+"#,
+        );
+    }
+
+    #[test]
+    fn test_no_hash() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("icons")).unwrap();
+        fs::write(asset_dir.join("favicon.ico"), b"favicon").unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        fs::write(asset_dir.join("icons/apple-touch-icon.png"), b"icon").unwrap();
+
+        Config::new()
+            .no_hash("favicon.ico")
+            .no_hash("icons/*")
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+
+        run_generated(
+            &out_path,
+            &format!(
+                r#"
+    assert_eq!(favicon_ico.name, "/static/favicon.ico");
+    assert_eq!(icons::apple_touch_icon_png.name, "/static/icons/apple-touch-icon.png");
+    assert_eq!(app_js.name, "/static/app-{:x}.js");
+    assert_eq!(StaticFile::get("/static/favicon.ico").unwrap(), &favicon_ico);
+    assert_eq!(favicon_ico.cache_control, "no-cache");
+    assert_eq!(app_js.cache_control, "public, max-age=31536000, immutable");
+"#,
+                md5::compute(b"app")
+            ),
+        );
+    }
+
+    #[test]
+    fn test_fixed_path() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join(".well-known")).unwrap();
+        fs::write(asset_dir.join("robots.txt"), b"User-agent: *").unwrap();
+        fs::write(asset_dir.join(".well-known/security.txt"), b"Contact: x").unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+
+        Config::new()
+            .url_prefix("/assets/")
+            .public_url_prefix("/cdn/")
+            .include_hidden(true)
+            .fixed_path("robots.txt")
+            .fixed_path(".well-known/*")
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+
+        run_generated(
+            &out_path,
+            &format!(
+                r#"
+    assert_eq!(robots_txt.name, "/robots.txt");
+    assert_eq!(robots_txt.url, "/robots.txt");
+    assert_eq!(robots_txt.cache_control, "no-cache");
+    assert_eq!(_well_known::security_txt.name, "/.well-known/security.txt");
+    assert_eq!(StaticFile::get("/robots.txt").unwrap(), &robots_txt);
+    assert_eq!(app_js.name, "/assets/app-{hash:x}.js");
+    assert_eq!(app_js.url, "/cdn/app-{hash:x}.js");
+"#,
+                hash = md5::compute(b"app")
+            ),
+        );
+    }
+
+    #[test]
+    fn test_doc_template() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("app")).unwrap();
+        fs::write(asset_dir.join("app/main.js"), b"main").unwrap();
+
+        Config::new()
+            .doc_template("Served at {url} as {mime}.\n\nHash {hash}, from {path} {other}")
+            .codegen(&out_path, std::slice::from_ref(&asset_dir), &[])
+            .unwrap();
+
+        let hash = format!("{:x}", md5::compute(b"main"));
+        let path = fs::canonicalize(asset_dir.join("app/main.js")).unwrap();
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains(&format!(
+            "    /// Served at /static/app/main-{hash}.js as application/javascript.\n    ///\n    /// Hash {hash}, from {} {{other}}\n    #[allow(non_upper_case_globals)]\n    pub static main_js",
+            path.display()
+        )));
+        assert!(!generated.contains("/// From"));
+        run_generated(&out_path, "assert_eq!(STATICS.len(), 1);");
+    }
+
+    #[test]
+    fn test_find_by_hash() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("app")).unwrap();
+        fs::write(asset_dir.join("app/main.js"), b"main").unwrap();
+        fs::write(asset_dir.join("favicon.ico"), b"favicon").unwrap();
+
+        Config::new()
+            .content_hashes(true)
+            .hash_length(12)
+            .no_hash("favicon.ico")
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+
+        let main = &format!("{:x}", md5::compute(b"main"))[..12];
+        let favicon = &format!("{:x}", md5::compute(b"favicon"))[..12];
+        run_generated(
+            &out_path,
+            &format!(
+                r#"
+    assert_eq!(app::main_js.hash, "{main}");
+    assert_eq!(StaticFile::find_by_hash("{main}").unwrap(), &app::main_js);
+    assert_eq!(StaticFile::find_by_hash("{favicon}").unwrap(), &favicon_ico);
+    assert!(StaticFile::find_by_hash("000000000000").is_none());
+"#
+            ),
+        );
+    }
+
+    #[test]
+    fn test_relative_includes() {
+        let dir = tempdir().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        let out_path = dir.path().join("src/static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("app")).unwrap();
+        fs::write(asset_dir.join("app/main.js"), b"main").unwrap();
+
+        Config::new()
+            .embed(true)
+            .relative_includes(true)
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains("content: include_bytes!(\"../assets/app/main.js\"),"));
+
+        // The assets and generated file still work together elsewhere.
+        let moved = tempdir().unwrap();
+        fs::create_dir_all(moved.path().join("src")).unwrap();
+        fs::create_dir_all(moved.path().join("assets/app")).unwrap();
+        fs::rename(&out_path, moved.path().join("src/static_gen.rs")).unwrap();
+        fs::write(moved.path().join("assets/app/main.js"), b"moved").unwrap();
+        run_generated(
+            &moved.path().join("src/static_gen.rs"),
+            "assert_eq!(app::main_js.content, b\"moved\");",
+        );
+    }
+
+    #[test]
+    fn test_validate_sources() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("app")).unwrap();
+        fs::write(asset_dir.join("app/main.js"), b"main").unwrap();
+        fs::write(asset_dir.join("app.css"), b"body {}").unwrap();
+
+        Config::new()
+            .codegen(&out_path, std::slice::from_ref(&asset_dir), &[])
+            .unwrap();
+        run_generated(
+            &out_path,
+            "assert_eq!(StaticFile::validate_sources(), Ok(()));",
+        );
+
+        fs::remove_file(asset_dir.join("app/main.js")).unwrap();
+        run_generated(
+            &out_path,
+            "assert_eq!(StaticFile::validate_sources(), Err(vec![app::main_js.file_name]));",
+        );
+
+        Config::new()
+            .embed(true)
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+        assert!(!fs::read_to_string(&out_path)
+            .unwrap()
+            .contains("validate_sources"));
+    }
+
+    #[test]
+    fn test_dependency() {
+        let dir = tempdir().unwrap();
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("partials")).unwrap();
+        fs::write(asset_dir.join("app.css"), b"@import 'partials/theme.css';").unwrap();
+        fs::write(asset_dir.join("partials/theme.css"), b"body { color: red }").unwrap();
+        let asset_dirs = [asset_dir.clone()];
+
+        let config = Config::new().dependency(
+            asset_dir.join("app.css"),
+            asset_dir.join("partials/theme.css"),
+        );
+        let app_url = |config: &Config| {
+            let manifest = config.manifest(&asset_dirs, &[]).unwrap();
+            Manifest::from_json(&manifest)
+                .unwrap()
+                .get("app.css")
+                .unwrap()
+                .to_string()
+        };
+
+        let plain = app_url(&Config::new());
+        let before = app_url(&config);
+        assert_ne!(before, plain);
+
+        fs::write(
+            asset_dir.join("partials/theme.css"),
+            b"body { color: blue }",
+        )
+        .unwrap();
+        assert_eq!(app_url(&Config::new()), plain);
+        let after = app_url(&config);
+        assert_ne!(after, before);
+
+        let err = Config::new()
+            .dependency(asset_dir.join("app.css"), asset_dir.join("missing.css"))
+            .manifest(&asset_dirs, &[])
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+        assert!(err.to_string().contains("missing.css"));
+    }
+
+    #[test]
+    fn test_fields() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("app")).unwrap();
+        fs::write(asset_dir.join("app/main.js"), b"main").unwrap();
+        fs::write(asset_dir.join("index.html"), b"<html>").unwrap();
+        let asset_dirs = [asset_dir];
+
+        Config::new()
+            .fields([])
+            .index_files(true)
+            .kinds(true)
+            .codegen(&out_path, &asset_dirs, &[])
+            .unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+        let start = generated.find("pub struct StaticFile {").unwrap();
+        let end = start + generated[start..].find('}').unwrap();
+        assert_eq!(
+            &generated[start..=end],
+            "pub struct StaticFile {\n    pub name: &'static str,\n    pub mime: &'static str,\n}"
+        );
+        for field in ["file_name:", "meta:", "cache_control:"] {
+            assert!(!generated.contains(field), "{field}");
+        }
+        run_generated(
+            &out_path,
+            r#"
+    assert_eq!(STATICS.len(), 2);
+    assert_eq!(StaticFile::get(app::main_js.name), Some(&app::main_js));
+    assert_eq!(app::main_js.mime, "application/javascript");
+    assert_eq!(StaticFile::get("/static/"), Some(&index_html));
+"#,
+        );
+
+        Config::new()
+            .fields([Field::CacheControl])
+            .codegen(&out_path, &asset_dirs, &[])
+            .unwrap();
+        run_generated(
+            &out_path,
+            r#"assert_eq!(index_html.cache_control, "no-cache");"#,
+        );
+
+        // Derives apply to whichever fields are generated.
+        Config::new()
+            .fields([])
+            .derives([Derive::Default, Derive::Copy, Derive::Clone])
+            .codegen(&out_path, &asset_dirs, &[])
+            .unwrap();
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(
+            generated.contains("#[derive(Debug, Clone, Copy, Default)]\npub struct StaticFile {")
+        );
+        run_generated(
+            &out_path,
+            r#"
+    let copy = index_html;
+    assert_eq!(copy, index_html.clone());
+    let custom = StaticFile { mime: "text/plain", ..StaticFile::default() };
+    assert_eq!(custom.name, "");
+"#,
+        );
+
+        for config in [
+            Config::new().derives([Derive::Copy]),
+            Config::new().fields([]).serve_root(true),
+            Config::new()
+                .fields([Field::FileName])
+                .meta("*.js", "defer", "true"),
+        ] {
+            let err = config.codegen(&out_path, &asset_dirs, &[]).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn test_modified_range() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        fs::write(asset_dir.join("stale.js"), b"stale").unwrap();
+        fs::write(asset_dir.join("fresh.js"), b"fresh").unwrap();
+        let stale_time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1);
+        File::options()
+            .write(true)
+            .open(asset_dir.join("stale.js"))
+            .unwrap()
+            .set_modified(stale_time)
+            .unwrap();
+        let (_file, extra_file) = create_temp_file(b"extra", "txt");
+        File::options()
+            .write(true)
+            .open(&extra_file)
+            .unwrap()
+            .set_modified(stale_time)
+            .unwrap();
+
+        let cutoff = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(86400);
+        let asset_dirs = [asset_dir];
+        for (config, included, skipped) in [
+            (Config::new().modified_after(cutoff), "fresh_js", "stale_js"),
+            (
+                Config::new().modified_before(cutoff),
+                "stale_js",
+                "fresh_js",
+            ),
+        ] {
+            config
+                .codegen(&out_path, &asset_dirs, std::slice::from_ref(&extra_file))
+                .unwrap();
+            let generated = fs::read_to_string(&out_path).unwrap();
+            assert!(generated.contains(&format!("pub static {included}: StaticFile")));
+            assert!(!generated.contains(skipped));
+            assert_eq!(generated.matches(": StaticFile = StaticFile {").count(), 2);
+        }
+    }
+
+    #[test]
+    fn test_nested_asset_dirs() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        let vendor_dir = asset_dir.join("vendor");
+        fs::create_dir_all(&vendor_dir).unwrap();
+        fs::write(asset_dir.join("main.js"), b"main").unwrap();
+        fs::write(vendor_dir.join("lib.js"), b"lib").unwrap();
+
+        for asset_dirs in [
+            vec![asset_dir.clone(), vendor_dir.clone()],
+            vec![vendor_dir.clone(), asset_dir.clone()],
+            vec![asset_dir.clone(), asset_dir.clone()],
+        ] {
+            Config::new().codegen(&out_path, &asset_dirs, &[]).unwrap();
+
+            let generated = fs::read_to_string(&out_path).unwrap();
+            assert_eq!(
+                generated.matches("pub static lib_js: StaticFile").count(),
+                1
+            );
+            assert!(generated.contains("pub mod vendor {"));
+            run_generated(
+                &out_path,
+                "assert_eq!(STATICS.len(), 2);\
+                 assert!(StaticFile::get(vendor::lib_js.name).is_some());",
+            );
+        }
+    }
+
+    #[test]
+    fn test_public_url_prefix() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("app")).unwrap();
+        fs::write(asset_dir.join("app/main.js"), b"main").unwrap();
+
+        Config::new()
+            .url_prefix("/assets/")
+            .public_url_prefix("/shop/assets")
+            .resolve(true)
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+
+        run_generated(
+            &out_path,
+            &format!(
+                r#"
+    let route = "/assets/app/main-{hash:x}.js";
+    let url = "/shop/assets/app/main-{hash:x}.js";
+    assert_eq!(app::main_js.route, route);
+    assert_eq!(app::main_js.url, url);
+    assert_eq!(app::main_js.to_string(), url);
+    assert_eq!(StaticFile::resolve("app/main.js"), Some(url));
+    assert_eq!(StaticFile::get(route).unwrap(), &app::main_js);
+    assert!(StaticFile::get(url).is_none());
+"#,
+                hash = md5::compute(b"main")
+            ),
+        );
+    }
+
+    #[test]
+    fn test_download() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("docs")).unwrap();
+        fs::write(asset_dir.join("docs/brochure.pdf"), b"%PDF").unwrap();
+        fs::write(asset_dir.join("docs/café menu.pdf"), b"%PDF menu").unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+
+        Config::new()
+            .download("*.pdf")
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+
+        run_generated(
+            &out_path,
+            r#"
+    assert_eq!(docs::brochure_pdf.disposition, Some("attachment; filename=\"brochure.pdf\""));
+    assert_eq!(
+        docs::caf_menu_pdf.disposition,
+        Some("attachment; filename=\"caf_ menu.pdf\"; filename*=UTF-8''caf%C3%A9%20menu.pdf")
+    );
+    assert_eq!(app_js.disposition, None);
+"#,
+        );
+    }
+
+    #[test]
+    fn test_preload() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("fonts")).unwrap();
+        fs::write(asset_dir.join("fonts/inter.woff2"), b"inter").unwrap();
+        fs::write(asset_dir.join("app.css"), b"app").unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+
+        Config::new()
+            .mime_for_glob("fonts/**", "font/woff2")
+            .preload("fonts/*")
+            .preload("*.css")
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+
+        run_generated(
+            &out_path,
+            r#"
+    assert!(fonts::inter_woff2.preload);
+    assert!(app_css.preload);
+    assert!(!app_js.preload);
+    assert_eq!(
+        app_css.preload_link().unwrap(),
+        format!("<link rel=\"preload\" href=\"{}\" as=\"style\">", app_css.name)
+    );
+    assert_eq!(
+        fonts::inter_woff2.preload_link().unwrap(),
+        format!(
+            "<link rel=\"preload\" href=\"{}\" as=\"font\" crossorigin>",
+            fonts::inter_woff2.name
+        )
+    );
+    assert_eq!(app_js.preload_link(), None);
+"#,
+        );
+    }
+
+    #[test]
+    fn test_header() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+        let (_file, file_path) = create_temp_file(b"header", "txt");
+        let extra_files = [file_path];
+
+        codegen(&out_path, &[], &extra_files).unwrap();
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.starts_with("// @generated by cacheb — do not edit\n\n#[allow("));
+
+        Config::new()
+            .header("Copyright Example Ltd.\n\nSPDX-License-Identifier: MIT")
+            .codegen(&out_path, &[], &extra_files)
+            .unwrap();
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.starts_with(
+            "// @generated by cacheb\n// Copyright Example Ltd.\n//\n// SPDX-License-Identifier: MIT\n\n#[allow("
+        ));
+        run_generated(&out_path, "assert_eq!(STATICS.len(), 1);");
+    }
+
+    #[test]
+    fn test_generated_marker() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+        let (_file, file_path) = create_temp_file(b"marker", "txt");
+        let extra_files = [file_path];
+
+        // rustfmt and review tools only look for the marker near the top,
+        // so it stays on the first line whatever the header.
+        let long_header = "line\n".repeat(10);
+        for config in [Config::new(), Config::new().header(long_header)] {
+            config.codegen(&out_path, &[], &extra_files).unwrap();
+            let generated = fs::read_to_string(&out_path).unwrap();
+            assert!(generated
+                .lines()
+                .next()
+                .unwrap()
+                .starts_with("// @generated"));
+
+            let mut written = Vec::new();
+            config
+                .codegen_to_writer(&mut written, &[], &extra_files)
+                .unwrap();
+            assert_eq!(String::from_utf8(written).unwrap(), generated);
+        }
+    }
+
+    #[test]
+    fn test_generated_layout() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("app/nested")).unwrap();
+        fs::create_dir_all(asset_dir.join("docs")).unwrap();
+        fs::write(asset_dir.join("root.css"), b"root").unwrap();
+        fs::write(asset_dir.join("app/nested/deep.js"), b"deep").unwrap();
+        fs::write(asset_dir.join("docs/index.html"), b"<html>").unwrap();
+        let asset_dirs = [asset_dir];
+
+        for (config, asset_dirs) in [
+            (Config::new(), &asset_dirs[..]),
+            (Config::new(), &[][..]),
+            (
+                Config::new()
+                    .header("first\n\n\nlast\n\n")
+                    .index_files(true)
+                    .const_refs(true)
+                    .kinds(true)
+                    .embed(true)
+                    .hoist_bytes(true)
+                    .url_macro(true)
+                    .resolve(true)
+                    .asset_enum(true)
+                    .categories(true)
+                    .serve_root(true)
+                    .generated_at(true),
+                &asset_dirs[..],
+            ),
+            (Config::new().no_std(true).flat(true), &asset_dirs[..]),
+        ] {
+            config.codegen(&out_path, asset_dirs, &[]).unwrap();
+            let generated = fs::read_to_string(&out_path).unwrap();
+
+            assert!(!generated.contains("\n\n\n"), "{generated}");
+            assert!(!generated.starts_with('\n'));
+            assert!(generated.ends_with('\n') && !generated.ends_with("\n\n"));
+            assert!(generated.lines().all(|line| line == line.trim_end()));
+        }
+    }
+
+    #[test]
+    fn test_display_implementation() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let test_content = b"test content for display";
+        let (_file, file_path) = create_temp_file(test_content, "txt");
+
+        codegen(&out_path, &[], &[file_path]).unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains("impl std::fmt::Display for StaticFile"));
+        assert!(generated
+            .contains("fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result"));
+    }
+
+    #[test]
+    fn test_get_method() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let test_content = b"test content for get method";
+        let (_file, file_path) = create_temp_file(test_content, "png");
+
+        codegen(&out_path, &[], &[file_path]).unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains("pub fn get(name: &str) -> Option<&'static Self>"));
+    }
+
+    #[test]
+    fn test_match_lookup() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("app/admin")).unwrap();
+        fs::create_dir(asset_dir.join("docs")).unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        fs::write(asset_dir.join("app/main.css"), b"main").unwrap();
+        fs::write(asset_dir.join("app/admin/admin.js"), b"admin").unwrap();
+        fs::write(asset_dir.join("docs/index.html"), b"docs").unwrap();
+
+        Config::new()
+            .match_lookup(true)
+            .index_files(true)
+            .cfg(asset_dir.join("app/admin"), "not(feature = \"never\")")
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains("match name {"));
+        assert!(!generated.contains("STATICS.iter().position"));
+
+        run_generated(
+            &out_path,
+            r#"assert_eq!(STATICS.len(), 4);
+            for file in STATICS {
+                assert_eq!(StaticFile::get(file.name), Some(*file));
+                assert_eq!(StaticFile::get(&format!("{}?v=1#top", file.name)), Some(*file));
+            }
+            assert_eq!(StaticFile::get("/static/docs/"), Some(&docs::index_html));
+            assert_eq!(StaticFile::get("/static/app.js"), None);"#,
+        );
+    }
+
+    #[test]
+    fn test_lazy_lookup() {
+        let dir = tempdir().unwrap();
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("docs")).unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        fs::write(asset_dir.join("docs/index.html"), b"index").unwrap();
+        let asset_dirs = [asset_dir];
+
+        for form in [UrlForm::Absolute, UrlForm::DotRelative] {
+            let eager_path = dir.path().join("eager_gen.rs");
+            let lazy_path = dir.path().join("lazy_gen.rs");
+            let config = Config::new().url_form(form).index_files(true);
+            config.codegen(&eager_path, &asset_dirs, &[]).unwrap();
+            config
+                .clone()
+                .lazy_lookup(true)
+                .codegen(&lazy_path, &asset_dirs, &[])
+                .unwrap();
+
+            run_program(&format!(
+                r#"mod eager {{
+    include!({eager_path:?});
+}}
+mod lazy {{
+    include!({lazy_path:?});
+}}
+
+fn main() {{
+    let mut names = eager::STATICS.iter().map(|file| file.name.to_string()).collect::<Vec<_>>();
+    names.extend(names.clone().iter().map(|name| format!("{{name}}?v=1")));
+    names.extend(["/static/docs/", "static/docs/", "/static/app.js", "", "/"].map(String::from));
+    for name in &names {{
+        assert_eq!(
+            eager::StaticFile::get(name).map(|file| file.name),
+            lazy::StaticFile::get(name).map(|file| file.name),
+            "{{name}}"
+        );
+    }}
+    assert!(lazy::StaticFile::get(eager::app_js.name).is_some());
+}}
+"#
+            ));
+        }
+
+        let err = Config::new()
+            .lazy_lookup(true)
+            .match_lookup(true)
+            .codegen(&dir.path().join("static_gen.rs"), &asset_dirs, &[])
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[cfg(feature = "phf")]
+    #[test]
+    fn test_phf_lookup() {
+        let dir = tempdir().unwrap();
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("docs")).unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        fs::write(asset_dir.join("docs/index.html"), b"index").unwrap();
+        let asset_dirs = [asset_dir];
+
+        for form in [UrlForm::Absolute, UrlForm::DotRelative] {
+            let array_path = dir.path().join("array_gen.rs");
+            let phf_path = dir.path().join("phf_gen.rs");
+            let config = Config::new().url_form(form).index_files(true);
+            config.codegen(&array_path, &asset_dirs, &[]).unwrap();
+            config
+                .clone()
+                .phf_lookup(true)
+                .codegen(&phf_path, &asset_dirs, &[])
+                .unwrap();
+            assert!(fs::read_to_string(&phf_path)
+                .unwrap()
+                .contains("static LOOKUP: phf::Map<&'static str, &'static StaticFile>"));
+
+            run_program_with_externs(
+                &format!(
+                    r#"mod array {{
+    include!({array_path:?});
+}}
+mod phf_map {{
+    include!({phf_path:?});
+}}
+
+fn main() {{
+    let mut names = array::STATICS.iter().map(|file| file.name.to_string()).collect::<Vec<_>>();
+    names.extend(names.clone().iter().map(|name| format!("{{name}}?v=1")));
+    names.extend(["/static/docs/", "static/docs/", "/static/app.js", "", "/"].map(String::from));
+    for name in &names {{
+        assert_eq!(
+            array::StaticFile::get(name).map(|file| file.name),
+            phf_map::StaticFile::get(name).map(|file| file.name),
+            "{{name}}"
+        );
+    }}
+    assert!(phf_map::StaticFile::get(array::app_js.name).is_some());
+}}
+"#
+                ),
+                &["phf"],
+            );
+        }
+
+        for config in [
+            Config::new().phf_lookup(true).lazy_lookup(true),
+            Config::new()
+                .phf_lookup(true)
+                .cfg("docs", "feature = \"docs\""),
+        ] {
+            let err = config
+                .codegen(&dir.path().join("static_gen.rs"), &asset_dirs, &[])
+                .unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        fs::create_dir(asset_dir.join("vendor")).unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        fs::write(asset_dir.join("vendor/script.js"), b"script").unwrap();
+
+        Config::new()
+            .resolve(true)
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+
+        let script = format!("/static/vendor/script-{:x}.js", md5::compute(b"script"));
+        run_generated(
+            &out_path,
+            &format!(
+                r#"assert_eq!(StaticFile::resolve("vendor/script.js"), Some("{script}"));
+                assert_eq!(StaticFile::resolve("app.js"), Some(app_js.name));
+                assert_eq!(StaticFile::resolve("script.js"), None);
+                assert_eq!(StaticFile::resolve("{script}"), None);"#
+            ),
+        );
+    }
+
+    #[test]
+    fn test_url_macro() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("vendor")).unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        fs::write(asset_dir.join("vendor/script.js"), b"script").unwrap();
+
+        Config::new()
+            .url_macro(true)
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+
+        let script = format!("/static/vendor/script-{:x}.js", md5::compute(b"script"));
+        run_program(&format!(
+            r#"mod statics {{
+    include!({out_path:?});
+}}
+
+mod page {{
+    pub const SCRIPT: &str = crate::statics::static_url!("vendor/script.js");
+}}
+
+fn main() {{
+    assert_eq!(page::SCRIPT, {script:?});
+    assert_eq!(statics::static_url!("app.js"), statics::app_js.name);
+}}
+"#
+        ));
+
+        assert_compile_error(
+            &format!(
+                r#"include!({out_path:?});
+
+fn main() {{
+    let _ = static_url!("vendor/missing.js");
+}}
+"#
+            ),
+            "no static file at vendor/missing.js",
+        );
+    }
+
+    #[test]
+    fn test_url_macro_matches_resolve() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("styles")).unwrap();
+        fs::write(asset_dir.join("styles/main.css"), b"main").unwrap();
+
+        // The build-time and runtime lookups frameworks like Leptos use give
+        // the same URL, including behind a public prefix.
+        Config::new()
+            .url_macro(true)
+            .resolve(true)
+            .public_url_prefix("/cdn/")
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+
+        let css = format!("/cdn/styles/main-{:x}.css", md5::compute(b"main"));
+        run_generated(
+            &out_path,
+            &format!(
+                r#"
+    let href: &'static str = static_url!("styles/main.css");
+    assert_eq!(href, {css:?});
+    assert_eq!(StaticFile::resolve("styles/main.css"), Some(href));
+    assert_eq!(StaticFile::resolve("styles/missing.css"), None);
+"#
+            ),
+        );
+    }
+
+    #[test]
+    fn test_zero_byte_file() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        fs::write(dir.path().join("empty.css"), b"").unwrap();
+
+        codegen(&out_path, &[dir.path().to_path_buf()], &[]).unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+
+        assert!(generated.contains("pub static empty_css"));
+        assert!(generated.contains("name: \"/static/empty-d41d8cd98f00b204e9800998ecf8427e.css\""));
+        assert!(generated.contains("mime: \"text/css\""));
+        assert!(generated.contains("&empty_css"));
+
+        // Embedded, it's an empty slice with a size of 0 in the manifest.
+        let config = Config::new().embed(true).manifest_sizes(true);
+        config
+            .codegen(&out_path, &[dir.path().to_path_buf()], &[])
+            .unwrap();
+        run_generated(
+            &out_path,
+            r#"
+    assert!(empty_css.content.is_empty());
+    assert_eq!(empty_css.content.len(), 0);
+"#,
+        );
+        let manifest = config.manifest(&[dir.path().to_path_buf()], &[]).unwrap();
+        let manifest = Manifest::from_json(&manifest).unwrap();
+        assert_eq!(manifest.size("empty.css", "identity"), Some(0));
+    }
+
+    #[test]
+    #[cfg(all(feature = "gzip", feature = "brotli"))]
+    fn test_zero_byte_file_compressed() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        fs::write(asset_dir.join("empty.css"), b"").unwrap();
+
+        // Nothing compresses smaller than nothing, so there are no variants.
+        Config::new()
+            .embed(true)
+            .gzip(true)
+            .brotli(true)
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+        run_generated(
+            &out_path,
+            r#"
+    assert_eq!(empty_css.content.len(), 0);
+    assert!(empty_css.gzip.is_none());
+    assert!(empty_css.brotli.is_none());
+    assert_eq!(empty_css.best_variant("br, gzip"), (&[][..], None));
+"#,
+        );
+    }
+
+    #[test]
+    fn test_matches_request() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        fs::write(dir.path().join("app.js"), b"console.log(1);").unwrap();
+
+        codegen(&out_path, &[dir.path().to_path_buf()], &[]).unwrap();
+
+        run_generated(
+            &out_path,
+            r#"
+    let file = &app_js;
+    assert!(file.matches_request(file.name));
+    assert!(file.matches_request(&format!("{}?v=2", file.name)));
+    assert!(file.matches_request(&format!("{}#top", file.name)));
+    assert!(!file.matches_request("/static/app.js"));
+    assert!(!file.matches_request(&format!("{}x", file.name)));
+    assert!(StaticFile::get(&format!("{}?v=2", file.name)).is_some());
+"#,
+        );
+    }
+
+    #[test]
+    fn test_sniff_mime() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        fs::write(
+            asset_dir.join("image.dat"),
+            b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR",
+        )
+        .unwrap();
+        fs::write(asset_dir.join("photo.png"), b"\xff\xd8\xff\xe0").unwrap();
+        fs::write(asset_dir.join("anim"), b"GIF89a").unwrap();
+        fs::write(asset_dir.join("hero"), b"RIFF\0\0\0\0WEBPVP8 ").unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        fs::write(asset_dir.join("tiny.pdf"), b"%PD").unwrap();
+        fs::write(asset_dir.join("forced.dat"), b"%PDF-1.7").unwrap();
+
+        Config::new()
+            .sniff_mime(true)
+            .mime_for_glob("forced.dat", "text/plain")
+            .codegen(&out_path, std::slice::from_ref(&asset_dir), &[])
+            .unwrap();
+        run_generated(
+            &out_path,
+            r#"
+    assert_eq!(image_dat.mime, "image/png");
+    assert_eq!(photo_png.mime, "image/jpeg");
+    assert_eq!(anim.mime, "image/gif");
+    assert_eq!(hero.mime, "image/webp");
+    assert_eq!(app_js.mime, "application/javascript");
+    assert_eq!(tiny_pdf.mime, "application/octet-stream");
+    assert_eq!(forced_dat.mime, "text/plain");
+"#,
+        );
+
+        codegen(&out_path, &[asset_dir], &[]).unwrap();
+        run_generated(
+            &out_path,
+            r#"assert_eq!(image_dat.mime, "application/octet-stream");"#,
+        );
+    }
+
+    #[test]
+    fn test_mime_fallback_custom() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let (_file, file_path) = create_temp_file(b"plain text", "unknown");
+
+        Config::new()
+            .mime_fallback(MimeFallback::Custom("text/plain".to_string()))
+            .codegen(&out_path, &[], &[file_path])
+            .unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains("mime: \"text/plain\""));
+        assert!(!generated.contains("application/octet-stream"));
+    }
+
+    #[test]
+    fn test_mime_fallback_error() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let (_file, known_path) = create_temp_file(b"body {}", "css");
+        let (_file, unknown_path) = create_temp_file(b"plain text", "unknown");
+
+        let config = Config::new().mime_fallback(MimeFallback::Error);
+        config
+            .codegen(&out_path, &[], std::slice::from_ref(&known_path))
+            .unwrap();
+
+        let err = config
+            .codegen(&out_path, &[], &[known_path, unknown_path.clone()])
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains(unknown_path.to_str().unwrap()));
+    }
+
+    #[test]
+    fn test_url_prefix() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let public_dir = dir.path().join("public");
+        let admin_dir = dir.path().join("admin");
+        fs::create_dir_all(public_dir.join("js")).unwrap();
+        fs::create_dir(&admin_dir).unwrap();
+        fs::write(public_dir.join("js/app.js"), b"app").unwrap();
+        fs::write(public_dir.join("index.html"), b"index").unwrap();
+        fs::write(admin_dir.join("admin.css"), b"admin").unwrap();
+        let asset_dirs = [public_dir.clone(), admin_dir.clone()];
+
+        Config::new()
+            .url_prefix("public")
+            .dir_url_prefix(&admin_dir, "/admin-assets/")
+            .index_files(true)
+            .codegen(&out_path, &asset_dirs, &[])
+            .unwrap();
+
+        let app = format!("/public/js/app-{:x}.js", md5::compute(b"app"));
+        let admin = format!("/admin-assets/admin-{:x}.css", md5::compute(b"admin"));
+        run_generated(
+            &out_path,
+            &format!(
+                r#"assert_eq!(js::app_js.name, "{app}");
+                assert_eq!(admin_css.name, "{admin}");
+                assert_eq!(StaticFile::get_index("/public/"), Some(&index_html));"#
+            ),
+        );
+
+        Config::new()
+            .url_prefix("/")
+            .codegen(&out_path, &asset_dirs, &[])
+            .unwrap();
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains(&format!(
+            "name: \"/admin-{:x}.css\"",
+            md5::compute(b"admin")
+        )));
+    }
+
+    #[test]
+    fn test_url_form() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("docs")).unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        fs::write(asset_dir.join("docs/index.html"), b"docs").unwrap();
+        let asset_dirs = [asset_dir];
+
+        let app = format!("static/app-{:x}.js", md5::compute(b"app"));
+        for (form, name) in [
+            (UrlForm::Absolute, format!("/{app}")),
+            (UrlForm::Relative, app.clone()),
+            (UrlForm::DotRelative, format!("./{app}")),
+        ] {
+            for match_lookup in [false, true] {
+                Config::new()
+                    .url_form(form)
+                    .match_lookup(match_lookup)
+                    .index_files(true)
+                    .base_url_env("CACHEB_TEST_URL_FORM_BASE")
+                    .codegen(&out_path, &asset_dirs, &[])
+                    .unwrap();
+                run_generated(
+                    &out_path,
+                    &format!(
+                        r#"assert_eq!(app_js.name, "{name}");
+                        assert_eq!(StaticFile::get("/{app}"), Some(&app_js));
+                        assert_eq!(StaticFile::get("{name}?v=1"), Some(&app_js));
+                        assert_eq!(StaticFile::get("/static/docs/"), Some(&docs::index_html));
+                        StaticFile::set_base_url("https://cdn.example.com/").unwrap();
+                        assert_eq!(app_js.url(), "https://cdn.example.com/{app}");"#
+                    ),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_cfg_gated_assets() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let admin_dir = dir.path().join("admin");
+        fs::create_dir(&admin_dir).unwrap();
+        fs::write(admin_dir.join("admin.js"), b"admin").unwrap();
+        fs::write(dir.path().join("app.js"), b"app").unwrap();
+
+        Config::new()
+            .cfg(&admin_dir, "feature = \"admin\"")
+            .codegen(&out_path, &[dir.path().to_path_buf()], &[])
+            .unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+
+        assert!(generated.contains("#[cfg(feature = \"admin\")]\npub mod admin {"));
+        assert!(generated.contains(
+            "    #[cfg(feature = \"admin\")]\n    #[allow(non_upper_case_globals)]\n    pub static admin_js"
+        ));
+        assert!(generated.contains("\n    #[cfg(feature = \"admin\")] &admin::admin_js"));
+        assert!(generated.contains("\n    &app_js"));
+
+        run_generated(&out_path, "assert_eq!(STATICS.len(), 1);");
+    }
+
+    #[test]
+    fn test_non_canonical_asset_dir() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("admin/users")).unwrap();
+        fs::create_dir(dir.path().join("other")).unwrap();
+        fs::write(asset_dir.join("admin/users/list.js"), b"list").unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+
+        let mut asset_dirs = vec![dir.path().join("other/../assets")];
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&asset_dir, dir.path().join("link")).unwrap();
+            asset_dirs.push(dir.path().join("link"));
+        }
+
+        for asset_dir_arg in asset_dirs {
+            // The options name the directory by its plain path.
+            Config::new()
+                .cfg(asset_dir.join("admin"), "feature = \"admin\"")
+                .dir_url_prefix(asset_dir.join("admin"), "/admin-static/")
+                .codegen(&out_path, &[asset_dir_arg], &[])
+                .unwrap();
+
+            let generated = fs::read_to_string(&out_path).unwrap();
+            assert!(generated.contains("#[cfg(feature = \"admin\")]\npub mod admin {"));
+            assert!(generated.contains("    pub mod users {"));
+            assert!(generated.contains("name: \"/admin-static/admin/users/list-"));
+            assert!(generated.contains("name: \"/static/app-"));
+            run_generated(&out_path, "assert_eq!(STATICS.len(), 1);");
+        }
+    }
+
+    #[test]
+    fn test_salt() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let test_content = b"salted content";
+        let (_file, file_path) = create_temp_file(test_content, "js");
+
+        let url_with_salt = |salt: &str| {
+            Config::new()
+                .salt(salt)
+                .codegen(&out_path, &[], std::slice::from_ref(&file_path))
+                .unwrap();
+            let generated = fs::read_to_string(&out_path).unwrap();
+            let start = generated.find(" name: \"").unwrap();
+            let end = generated[start..].find('\n').unwrap();
+            generated[start..start + end].to_string()
+        };
+
+        let unsalted = url_with_salt("");
+        assert!(unsalted.contains(&format!("{:x}", md5::compute(test_content))));
+
+        let v1 = url_with_salt("v1");
+        let v2 = url_with_salt("v2");
+        assert_ne!(v1, unsalted);
+        assert_ne!(v1, v2);
+        assert_eq!(v1, url_with_salt("v1"));
+    }
+
+    #[test]
+    fn test_hash_buffer_size() {
+        let dir = tempdir().unwrap();
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        let content = "chunked content ".repeat(50);
+        fs::write(asset_dir.join("app.js"), &content).unwrap();
+        let asset_dirs = [asset_dir];
+
+        let generate = |config: Config| {
+            let mut code = Vec::new();
+            config
+                .salt("salt")
+                .etag(HashAlgorithm::Sha256)
+                .codegen_to_writer(&mut code, &asset_dirs, &[])
+                .unwrap();
+            String::from_utf8(code).unwrap()
+        };
+        let expected = generate(Config::new());
+        let url = format!(
+            "/static/app-{:x}.js",
+            md5::compute(format!("salt{content}"))
+        );
+        assert!(expected.contains(&format!("name: \"{url}\"")));
+
+        // Tiny buffers take many reads to get through the file.
+        for buffer_size in [0, 1, 7, 1 << 20] {
+            let code = generate(Config::new().hash_buffer_size(buffer_size));
+            assert_eq!(code, expected);
+        }
+    }
+
+    #[test]
+    fn test_interrupted_reads() {
+        /// Yields `content` a byte at a time, interrupted before each.
+        struct Interrupting<'a> {
+            content: &'a [u8],
+            interrupt: bool,
+        }
+
+        impl Read for Interrupting<'_> {
+            fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+                self.interrupt = !self.interrupt;
+                if self.interrupt {
+                    return Err(std::io::ErrorKind::Interrupted.into());
+                }
+                let Some((first, rest)) = self.content.split_first() else {
+                    return Ok(0);
+                };
+                buffer[0] = *first;
+                self.content = rest;
+                Ok(1)
+            }
+        }
+
+        let content = b"interrupted content";
+        let reader = Interrupting {
+            content,
+            interrupt: false,
+        };
+        let digests =
+            calculate_hashes(reader, "", vec![Hasher::new(HashAlgorithm::Md5)], 16).unwrap();
+        assert_eq!(digests, [md5::compute(content).0.to_vec()]);
+
+        struct Failing;
+
+        impl Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("stale file handle"))
+            }
+        }
+
+        let hashers = vec![Hasher::new(HashAlgorithm::Md5)];
+        let err = calculate_hashes(Failing, "", hashers, 16).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Other);
+
+        let dir = tempdir().unwrap();
+        let missing = dir.path().join("missing.js");
+        let config = Config::new();
+        let err = Generator::new(&config, None, None)
+            .unwrap()
+            .digests(&missing, None)
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+        assert!(err.to_string().starts_with(&missing.display().to_string()));
+
+        // A missing extra file fails before it's read, naming it too.
+        for config in [Config::new(), Config::new().warn_size(1)] {
+            let err = config
+                .codegen(
+                    &dir.path().join("static_gen.rs"),
+                    &[],
+                    std::slice::from_ref(&missing),
+                )
+                .unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+            assert!(err.to_string().starts_with(&missing.display().to_string()));
+        }
+    }
+
+    #[test]
+    fn test_normalize_line_endings() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+
+        let urls = |config: &Config, css: &[u8], png: &[u8]| {
+            fs::write(asset_dir.join("main.css"), css).unwrap();
+            fs::write(asset_dir.join("logo.png"), png).unwrap();
+            config
+                .codegen(&out_path, std::slice::from_ref(&asset_dir), &[])
+                .unwrap();
+            let generated = fs::read_to_string(&out_path).unwrap();
+            generated
+                .lines()
+                .filter(|line| line.trim_start().starts_with("name: "))
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+
+        let config = Config::new().normalize_line_endings(true);
+        let crlf = urls(&config, b"a {}\r\nb {}\r\n", b"\x89PNG\r\n");
+        let lf = urls(&config, b"a {}\nb {}\n", b"\x89PNG\n");
+        // logo.png sorts first, then main.css.
+        assert_ne!(crlf[0], lf[0]);
+        assert_eq!(crlf[1], lf[1]);
+        assert!(lf[1].contains(&format!("{:x}", md5::compute(b"a {}\nb {}\n"))));
+        assert_ne!(
+            urls(&config, b"a {}\rb {}\r", b""),
+            urls(&config, b"a {}\nb {}\n", b"")
+        );
+
+        let crlf = urls(&Config::new(), b"a {}\r\n", b"");
+        let lf = urls(&Config::new(), b"a {}\n", b"");
+        assert_ne!(crlf[1], lf[1]);
+    }
+
+    #[test]
+    fn test_index_files() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let docs_dir = dir.path().join("docs");
+        fs::create_dir(&docs_dir).unwrap();
+        fs::write(docs_dir.join("index.html"), b"<h1>Docs</h1>").unwrap();
+        fs::write(dir.path().join("app.js"), b"app").unwrap();
+
+        Config::new()
+            .index_files(true)
+            .codegen(&out_path, &[dir.path().to_path_buf()], &[])
+            .unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains("(\"/static/docs/\", &docs::index_html)"));
+
+        run_generated(
+            &out_path,
+            r#"
+    let index = StaticFile::get("/static/docs/").unwrap();
+    assert!(std::ptr::eq(index, &docs::index_html));
+    assert!(index.name.starts_with("/static/docs/index-"));
+    assert_eq!(index.mime, "text/html");
+    assert!(StaticFile::get("/static/docs/?v=1").is_some());
+    assert!(StaticFile::get("/static/").is_none());
+    assert!(StaticFile::get_index("/static/docs").is_none());
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hash_encoding() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        fs::write(dir.path().join("empty.js"), b"").unwrap();
+
+        let hash_with = |config: Config| {
+            config
+                .codegen(&out_path, &[dir.path().to_path_buf()], &[])
+                .unwrap();
+            let generated = fs::read_to_string(&out_path).unwrap();
+            let start = generated.find("\"/static/empty-").unwrap() + 15;
+            let end = generated[start..].find(".js\"").unwrap();
+            generated[start..start + end].to_string()
+        };
+
+        let hex = hash_with(Config::new());
+        let upper_hex = hash_with(Config::new().hash_encoding(HashEncoding::UpperHex));
+        let base32 = hash_with(Config::new().hash_encoding(HashEncoding::Base32));
+        let base62 = hash_with(Config::new().hash_encoding(HashEncoding::Base62));
+        let short = hash_with(
+            Config::new()
+                .hash_encoding(HashEncoding::Base62)
+                .hash_length(8),
+        );
+
+        assert_eq!(hex, "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(upper_hex, "D41D8CD98F00B204E9800998ECF8427E");
+        assert_eq!(base32, "2qoyzwmpaczaj2mabgmoz6ccpy");
+        assert_eq!(base62, "6SFsQFoWZKg7DZ3I6vLDPK");
+        assert_eq!(short, "6SFsQFoW");
+
+        for hash in [hex, upper_hex, base32, base62, short] {
+            assert!(hash.chars().all(|c| c.is_ascii_alphanumeric()));
+        }
+    }
+
+    #[test]
+    fn test_generated_at() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        codegen(&out_path, &[], &[]).unwrap();
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(!generated.contains("GENERATED_AT"));
+
+        Config::new()
+            .generated_at(true)
+            .codegen(&out_path, &[], &[])
+            .unwrap();
+        let generated = fs::read_to_string(&out_path).unwrap();
+
+        let start = generated.find("pub const GENERATED_AT: &str = \"").unwrap() + 32;
+        let timestamp = &generated[start..start + 20];
+
+        let (date, time) = timestamp
+            .strip_suffix('Z')
+            .unwrap()
+            .split_once('T')
+            .unwrap();
+        let date: Vec<u32> = date.split('-').map(|part| part.parse().unwrap()).collect();
+        let time: Vec<u32> = time.split(':').map(|part| part.parse().unwrap()).collect();
+
+        assert!(date[0] >= 2025);
+        assert!((1..=12).contains(&date[1]) && (1..=31).contains(&date[2]));
+        assert!(time[0] < 24 && time[1] < 60 && time[2] < 60);
+        assert_eq!(&generated[start + 20..start + 21], "\"");
+
+        run_generated(&out_path, "assert_eq!(GENERATED_AT.len(), 20);");
+    }
+
+    #[test]
+    fn test_meta() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let fonts_dir = dir.path().join("fonts");
+        fs::create_dir(&fonts_dir).unwrap();
+        fs::write(fonts_dir.join("inter.woff2"), b"font").unwrap();
+        fs::write(dir.path().join("app.js"), b"app").unwrap();
+
+        Config::new()
+            .meta("fonts/**", "preload", "true")
+            .meta("*.woff2", "category", "font \"inter\"")
+            .codegen(&out_path, &[dir.path().to_path_buf()], &[])
+            .unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains("meta: &[],"));
+
+        run_generated(
+            &out_path,
+            r#"
+    assert_eq!(
+        fonts::inter_woff2.meta,
+        &[("preload", "true"), ("category", "font \"inter\"")]
+    );
+    assert!(app_js.meta.is_empty());
+"#,
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_path() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        fs::write(asset_dir.join(OsStr::from_bytes(b"bad\xff.js")), b"bad").unwrap();
+
+        let err = codegen(&out_path, &[asset_dir], &[]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("not valid UTF-8"));
+        assert!(err.to_string().contains("bad\u{FFFD}.js"));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_case_collisions() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("img")).unwrap();
+        fs::write(asset_dir.join("img/Logo.png"), b"upper").unwrap();
+        fs::write(asset_dir.join("img/logo.png"), b"lower").unwrap();
+        fs::write(asset_dir.join("img/icon.png"), b"icon").unwrap();
+        let asset_dirs = [asset_dir.clone()];
+
+        let report = Config::new().codegen(&out_path, &asset_dirs, &[]).unwrap();
+        assert_eq!(
+            report.case_collisions,
+            [(
+                asset_dir.join("img/Logo.png"),
+                asset_dir.join("img/logo.png")
+            )]
+        );
+
+        let err = Config::new()
+            .deny_case_collisions(true)
+            .codegen(&out_path, &asset_dirs, &[])
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("differ only by case"));
+
+        // Different URL prefixes keep them apart.
+        let report = Config::new()
+            .dir_url_prefix(asset_dir.join("img/Logo.png"), "/brand/")
+            .deny_case_collisions(true)
+            .codegen(&out_path, &asset_dirs, &[])
+            .unwrap();
+        assert!(report.case_collisions.is_empty());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_unportable_names() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        fs::write(asset_dir.join("app.js "), b"app").unwrap();
+        fs::write(asset_dir.join("main.css.."), b"main").unwrap();
+        fs::write(asset_dir.join("..."), b"dots").unwrap();
+
+        let report = Config::new()
+            .include_hidden(true)
+            .codegen(&out_path, std::slice::from_ref(&asset_dir), &[])
+            .unwrap();
+        assert_eq!(
+            report.unportable_names,
+            [asset_dir.join("app.js "), asset_dir.join("main.css..")]
+        );
+
+        let app = format!("/static/app-{:x}.js", md5::compute(b"app"));
+        let main = format!("/static/main-{:x}.css", md5::compute(b"main"));
+        run_generated(
+            &out_path,
+            &format!(
+                r#"assert_eq!(app_js.name, "{app}");
+                assert_eq!(app_js.mime, "application/javascript");
+                assert_eq!(main_css.name, "{main}");
+                assert_eq!(std::fs::read(app_js.file_name).unwrap(), b"app");
+                assert_eq!(STATICS.len(), 3);"#
+            ),
+        );
+    }
+
+    #[test]
+    fn test_extensionless_file() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        fs::write(dir.path().join("LICENSE"), b"MIT").unwrap();
+
+        codegen(&out_path, &[dir.path().to_path_buf()], &[]).unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+        let hash = format!("{:x}", md5::compute(b"MIT"));
+        assert!(generated.contains(&format!("name: \"/static/LICENSE-{hash}\",")));
+        assert!(generated.contains("mime: \"application/octet-stream\""));
+    }
+
+    #[test]
+    fn test_const_contexts() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let vendor_dir = dir.path().join("vendor");
+        fs::create_dir(&vendor_dir).unwrap();
+        fs::write(vendor_dir.join("script.js"), b"script").unwrap();
+        fs::write(dir.path().join("main.css"), b"main").unwrap();
+
+        codegen(&out_path, &[dir.path().to_path_buf()], &[]).unwrap();
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(!generated.contains("pub const"));
+
+        run_generated(
+            &out_path,
+            r#"
+    const PRELOAD: &[&StaticFile] = &[&main_css, &vendor::script_js];
+    static PRELOAD_STATIC: &[&StaticFile] = &[&vendor::script_js];
+    const MAIN_CSS_NAME: &str = main_css.name;
+    assert_eq!(PRELOAD[1].name, PRELOAD_STATIC[0].name);
+    assert_eq!(MAIN_CSS_NAME, main_css.name);
+"#,
+        );
+
+        Config::new()
+            .const_refs(true)
+            .codegen(&out_path, &[dir.path().to_path_buf()], &[])
+            .unwrap();
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains("pub const MAIN_CSS: &StaticFile = &main_css;"));
+        assert!(generated.contains("    pub const SCRIPT_JS: &StaticFile = &script_js;"));
+
+        run_generated(
+            &out_path,
+            r#"
+    const PRELOAD: &[&StaticFile] = &[MAIN_CSS, vendor::SCRIPT_JS];
+    assert!(std::ptr::eq(PRELOAD[1], &vendor::script_js));
+"#,
+        );
+
+        // Files whose consts would clash are refused, naming both.
+        for (first, second) in [("A.js", "a.js"), ("FOO", "foo")] {
+            let dir = tempdir().unwrap();
+            fs::write(dir.path().join(first), b"first").unwrap();
+            fs::write(dir.path().join(second), b"second").unwrap();
+            for config in [Config::new().const_refs(true), Config::new().routes(true)] {
+                let err = config
+                    .codegen(&out_path, &[dir.path().to_path_buf()], &[])
+                    .unwrap_err();
+                assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput, "{err}");
+                assert!(err.to_string().contains(first), "{err}");
+                assert!(err.to_string().contains(second), "{err}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_no_std() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("docs")).unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        fs::write(asset_dir.join("docs/index.html"), b"index").unwrap();
+        let asset_dirs = [asset_dir];
+
+        let config = Config::new()
+            .no_std(true)
+            .embed(true)
+            .etag(HashAlgorithm::Md5)
+            .kinds(true)
+            .routes(true)
+            .index_files(true)
+            .fallback(asset_dirs[0].join("docs/index.html"))
+            .resolve(true)
+            .url_macro(true);
+        for config in [config.clone(), config.match_lookup(true)] {
+            config.codegen(&out_path, &asset_dirs, &[]).unwrap();
+            assert!(!fs::read_to_string(&out_path).unwrap().contains("std::"));
+            assert_compiles_no_std(&out_path);
+        }
+
+        let err = Config::new()
+            .no_std(true)
+            .serve_root(true)
+            .codegen(&out_path, &asset_dirs, &[])
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("Config::serve_root needs std"));
+    }
+
+    #[test]
+    fn test_no_std_docs() {
+        let source = include_str!("lib.rs");
+        let end = source.find("    pub fn no_std(").unwrap();
+        let start = source[..end].rfind("\n\n").unwrap();
+        let docs = source[start..end].replace("\n    /// ", " ");
+        for (option, _) in Config::new().options_needing_std() {
+            assert!(docs.contains(&format!("`{option}`")), "{option}");
+        }
+    }
+
+    #[test]
+    fn test_strip_prefix() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let app_dir = dir.path().join("dist").join("app");
+        fs::create_dir_all(&app_dir).unwrap();
+        fs::write(app_dir.join("main.js"), b"main").unwrap();
+
+        Config::new()
+            .strip_prefix("dist")
+            .codegen(&out_path, &[dir.path().to_path_buf()], &[])
+            .unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains("name: \"/static/app/main-"));
+        assert!(!generated.contains("/static/dist"));
+        assert!(!generated.contains("pub mod dist"));
+        assert!(generated.contains("&app::main_js"));
+
+        run_generated(
+            &out_path,
+            "assert!(app::main_js.name.starts_with(\"/static/app/\"));",
+        );
+    }
+
+    #[test]
+    fn test_etag_algorithm() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let test_content = b"etag content";
+        fs::write(dir.path().join("app.js"), test_content).unwrap();
+
+        Config::new()
+            .etag(HashAlgorithm::Sha256)
+            .codegen(&out_path, &[dir.path().to_path_buf()], &[])
+            .unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+
+        let md5 = format!("{:x}", md5::compute(test_content));
+        let sha256 = HashEncoding::Hex.encode(&sha2::Sha256::digest(test_content));
+        assert_ne!(md5, sha256);
+        assert!(generated.contains(&format!("name: \"/static/app-{md5}.js\"")));
+        assert!(generated.contains(&format!("etag: \"\\\"{sha256}\\\"\"")));
+
+        run_generated(
+            &out_path,
+            &format!("assert_eq!(app_js.etag, \"\\\"{sha256}\\\"\");"),
+        );
+
+        Config::new()
+            .hash_algorithm(HashAlgorithm::Sha256)
+            .codegen(&out_path, &[dir.path().to_path_buf()], &[])
+            .unwrap();
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains(&format!("name: \"/static/app-{sha256}.js\"")));
+        assert!(!generated.contains("etag"));
+
+        Config::new()
+            .etag(HashAlgorithm::Sha256)
+            .etag_uppercase(true)
+            .codegen(&out_path, &[dir.path().to_path_buf()], &[])
+            .unwrap();
+        let generated = fs::read_to_string(&out_path).unwrap();
+        let upper = sha256.to_ascii_uppercase();
+        assert!(generated.contains(&format!("name: \"/static/app-{md5}.js\"")));
+        assert!(generated.contains(&format!("etag: \"\\\"{upper}\\\"\"")));
+    }
+
+    #[test]
+    #[cfg(feature = "blake3")]
+    fn test_blake3() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let test_content = b"blake3 content";
+        fs::write(dir.path().join("app.js"), test_content).unwrap();
+        let blake3 = blake3::hash(test_content).to_hex().to_string();
+
+        Config::new()
+            .hash_algorithm(HashAlgorithm::Blake3)
+            .etag(HashAlgorithm::Blake3)
+            .codegen(&out_path, &[dir.path().to_path_buf()], &[])
+            .unwrap();
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains(&format!("name: \"/static/app-{blake3}.js\"")));
+        assert!(generated.contains(&format!("etag: \"\\\"{blake3}\\\"\"")));
+
+        Config::new()
+            .hash_algorithm(HashAlgorithm::Blake3)
+            .hash_encoding(HashEncoding::Base62)
+            .hash_length(8)
+            .codegen(&out_path, &[dir.path().to_path_buf()], &[])
+            .unwrap();
+        let base62 = HashEncoding::Base62.encode(blake3::hash(test_content).as_bytes());
+        assert_eq!(base62.len(), 43);
+        run_generated(
+            &out_path,
+            &format!(
+                "assert_eq!(app_js.name, \"/static/app-{}.js\");",
+                &base62[..8]
+            ),
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "gzip", feature = "brotli"))]
+    fn test_best_variant() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        let script = "console.log(\"hello\");\n".repeat(64);
+        fs::write(asset_dir.join("app.js"), &script).unwrap();
+        fs::write(asset_dir.join("logo.png"), script.as_bytes()).unwrap();
+
+        Config::new()
+            .embed(true)
+            .gzip(true)
+            .brotli(true)
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains("content: include_bytes!("));
+        assert!(generated.contains("gzip: Some(b\"\\x1f\\x8b"));
+        assert!(generated.contains("gzip: None"));
+        assert!(generated.contains("brotli: None"));
+
+        run_generated(
+            &out_path,
+            &format!(
+                r#"
+    let script = {script:?};
+    assert_eq!(app_js.content, script.as_bytes());
+    assert!(app_js.gzip.unwrap().len() < script.len());
+
+    let (bytes, encoding) = app_js.best_variant("gzip, deflate, br");
+    assert_eq!(encoding, Some("br"));
+    assert_eq!(bytes, app_js.brotli.unwrap());
+
+    let (bytes, encoding) = app_js.best_variant("br;q=0, gzip;q=0.5");
+    assert_eq!(encoding, Some("gzip"));
+    assert_eq!(&bytes[..2], b"\x1f\x8b");
+
+    assert_eq!(app_js.best_variant("*;q=0.1").1, Some("br"));
+    assert_eq!(app_js.best_variant("br;q=0, *").1, Some("gzip"));
+    assert_eq!(app_js.best_variant("identity").1, None);
+    assert_eq!(app_js.best_variant("").0, script.as_bytes());
+    assert_eq!(logo_png.best_variant("br, gzip"), (logo_png.content, None));
+"#
+            ),
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "gzip")]
+    fn test_compress_extension() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        let text = "plain text, not an image\n".repeat(64);
+        fs::write(asset_dir.join("data.PNG"), &text).unwrap();
+        fs::write(asset_dir.join("icon.svg"), &text).unwrap();
+        fs::write(asset_dir.join("logo.png"), &text).unwrap();
+
+        Config::new()
+            .gzip(true)
+            .compress_extension("png", true)
+            .compress_extension("svg", false)
+            .compress_extension("png", false)
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+
+        run_generated(
+            &out_path,
+            r#"
+    assert!(data_PNG.gzip.is_some());
+    assert!(logo_png.gzip.is_some());
+    assert!(icon_svg.gzip.is_none());
+"#,
+        );
+    }
+
+    #[test]
+    fn test_hoist_bytes() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("app/nested")).unwrap();
+        fs::write(asset_dir.join("root.css"), b"root").unwrap();
+        fs::write(asset_dir.join("app/nested/main.js"), b"main").unwrap();
+
+        Config::new()
+            .embed(true)
+            .hoist_bytes(true)
+            .codegen(&out_path, std::slice::from_ref(&asset_dir), &[])
+            .unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+        let main = fs::canonicalize(asset_dir.join("app/nested/main.js")).unwrap();
+        assert!(generated.contains(&format!(
+            "static BYTES_1: &[u8] = include_bytes!({main:?});"
+        )));
+        assert!(generated.contains("content: BYTES_0,"));
+        assert!(generated.contains("content: super::super::BYTES_1,"));
+        assert!(!generated.contains("content: include_bytes!"));
+
+        run_generated(
+            &out_path,
+            r#"
+    assert_eq!(root_css.content, b"root");
+    assert_eq!(app::nested::main_js.content, b"main");
+"#,
+        );
+    }
+
+    #[test]
+    fn test_inline() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        fs::write(asset_dir.join("critical.css"), b"body { margin: 0 }").unwrap();
+        let asset_dirs = [asset_dir];
+
+        Config::new().codegen(&out_path, &asset_dirs, &[]).unwrap();
+        assert!(!fs::read_to_string(&out_path)
+            .unwrap()
+            .contains("fn inline("));
+
+        Config::new()
+            .embed(true)
+            .codegen(&out_path, &asset_dirs, &[])
+            .unwrap();
+        run_generated(
+            &out_path,
+            r#"
+    let (mime, content) = critical_css.inline();
+    assert_eq!(mime, "text/css");
+    assert_eq!(content, b"body { margin: 0 }");
+"#,
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "brotli")]
+    fn test_compress_embedded() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        let script = "console.log(\"hello\");\n".repeat(64);
+        fs::write(asset_dir.join("app.js"), &script).unwrap();
+
+        Config::new()
+            .compress_embedded(true)
+            .brotli(true)
+            .derives([Derive::Clone])
+            .codegen(&out_path, std::slice::from_ref(&asset_dir), &[])
+            .unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(!generated.contains("include_bytes!"));
+        let start = generated.find("compressed_content: b\"").unwrap();
+        let end = start + generated[start..].find('\n').unwrap();
+        assert!(end - start < script.len());
+
+        run_program_with_externs(
+            &format!(
+                r#"include!({out_path:?});
+
+fn main() {{
+    let script = {script:?};
+    assert_eq!(app_js.content(), script.as_bytes());
+    assert!(std::ptr::eq(app_js.content(), app_js.content()));
+    assert_eq!(app_js.inline(), ("application/javascript", script.as_bytes()));
+    assert_eq!(app_js.best_variant("gzip").0, script.as_bytes());
+    assert_eq!(app_js.best_variant("br").1, Some("br"));
+    assert_eq!(app_js.clone(), app_js);
+}}
+"#
+            ),
+            &["brotli"],
+        );
+
+        // The content cache can't be copied.
+        let err = Config::new()
+            .compress_embedded(true)
+            .derives([Derive::Clone, Derive::Copy])
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_hash_placement_directory() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("scripts")).unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        fs::write(asset_dir.join("scripts/main.min.js"), b"main").unwrap();
+
+        Config::new()
+            .hash_placement(HashPlacement::Directory)
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+
+        let app = format!("/static/{:x}/app.js", md5::compute(b"app"));
+        let main = format!("/static/{:x}/scripts/main.min.js", md5::compute(b"main"));
+        run_generated(
+            &out_path,
+            &format!(
+                r#"
+    assert_eq!(app_js.name, {app:?});
+    assert_eq!(scripts::main_min_js.name, {main:?});
+    assert!(StaticFile::get({main:?}).is_some());
+"#
+            ),
+        );
+    }
+
+    #[test]
+    fn test_serve_extension() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        fs::write(asset_dir.join("app.ts"), b"app").unwrap();
+        fs::write(asset_dir.join("View.JSX"), b"view").unwrap();
+        fs::write(asset_dir.join("main.css"), b"main").unwrap();
+
+        Config::new()
+            .serve_extension("ts", "js")
+            .serve_extension("jsx", "js")
+            .codegen(&out_path, std::slice::from_ref(&asset_dir), &[])
+            .unwrap();
+
+        let app = format!("/static/app-{:x}.js", md5::compute(b"app"));
+        let view = format!("/static/View-{:x}.js", md5::compute(b"view"));
+        let app_source = fs::canonicalize(asset_dir.join("app.ts")).unwrap();
+        run_generated(
+            &out_path,
+            &format!(
+                r#"
+    assert_eq!(app_ts.name, {app:?});
+    assert_eq!(app_ts.mime, "application/javascript");
+    assert_eq!(app_ts.file_name, {app_source:?});
+    assert_eq!(View_JSX.name, {view:?});
+    assert_eq!(View_JSX.mime, "application/javascript");
+    assert_eq!(main_css.mime, "text/css");
+"#
+            ),
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "axum")]
+    fn test_axum_response() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+
+        let err = Config::new()
+            .axum(true)
+            .codegen(&out_path, std::slice::from_ref(&asset_dir), &[])
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+        Config::new()
+            .embed(true)
+            .etag(HashAlgorithm::Md5)
+            .axum(true)
+            .codegen(&out_path, std::slice::from_ref(&asset_dir), &[])
+            .unwrap();
+
+        run_program_with_externs(
+            &format!(
+                r#"include!({out_path:?});
+
+use axum::http::{{header, HeaderMap, HeaderValue, StatusCode}};
+use axum::response::IntoResponse;
+
+fn main() {{
+    let response = app_js.into_response();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/javascript");
+    assert_eq!(response.headers()[header::ETAG], app_js.etag);
+    assert_eq!(
+        response.headers()[header::CACHE_CONTROL],
+        "public, max-age=31536000, immutable"
+    );
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"stale\""));
+    assert_eq!(app_js.response(&headers).status(), StatusCode::OK);
+
+    let matching = format!("\"stale\", W/{{}}", app_js.etag);
+    headers.insert(header::IF_NONE_MATCH, HeaderValue::from_str(&matching).unwrap());
+    let response = app_js.response(&headers);
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers()[header::ETAG], app_js.etag);
+
+    let response = StaticFile::serve(app_js.name, &headers);
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    let response = StaticFile::serve("/static/missing.js", &headers);
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert!(response.headers().get(header::CONTENT_TYPE).is_none());
+}}
+"#
+            ),
+            &["axum"],
+        );
+
+        fs::write(asset_dir.join("brochure.pdf"), b"%PDF").unwrap();
+        Config::new()
+            .embed(true)
+            .axum(true)
+            .download("*.pdf")
+            .not_found_body("text/html", "<h1>Not here</h1>")
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+        run_program_with_externs(
+            &format!(
+                r#"include!({out_path:?});
+
+use std::future::Future;
+
+fn main() {{
+    let response = StaticFile::serve("/static/missing.js", &axum::http::HeaderMap::new());
+    assert_eq!(response.status(), axum::http::StatusCode::NOT_FOUND);
+    assert_eq!(response.headers()[axum::http::header::CONTENT_TYPE], "text/html");
+    let body = std::pin::pin!(axum::body::to_bytes(response.into_body(), usize::MAX));
+    let std::task::Poll::Ready(body) =
+        body.poll(&mut std::task::Context::from_waker(std::task::Waker::noop()))
+    else {{
+        panic!("the body is in memory");
+    }};
+    assert_eq!(&body.unwrap()[..], b"<h1>Not here</h1>");
+
+    let response = axum::response::IntoResponse::into_response(&app_js);
+    assert_eq!(response.status(), axum::http::StatusCode::OK);
+    assert!(response.headers().get(axum::http::header::ETAG).is_none());
+    assert!(response.headers().get(axum::http::header::CONTENT_DISPOSITION).is_none());
+
+    let response = axum::response::IntoResponse::into_response(&brochure_pdf);
+    assert_eq!(
+        response.headers()[axum::http::header::CONTENT_DISPOSITION],
+        "attachment; filename=\"brochure.pdf\""
+    );
+}}
+"#
+            ),
+            &["axum"],
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "http")]
+    fn test_http_response() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        fs::write(asset_dir.join("brochure.pdf"), b"%PDF").unwrap();
+
+        let err = Config::new()
+            .http(true)
+            .codegen(&out_path, std::slice::from_ref(&asset_dir), &[])
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+
+        Config::new()
+            .embed(true)
+            .etag(HashAlgorithm::Md5)
+            .download("*.pdf")
+            .http(true)
+            .codegen(&out_path, std::slice::from_ref(&asset_dir), &[])
+            .unwrap();
+
+        run_program_with_externs(
+            &format!(
+                r#"include!({out_path:?});
+
+use http::{{header, HeaderMap, HeaderValue, StatusCode}};
+
+fn main() {{
+    let response = app_js.into_http_response(&HeaderMap::new());
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/javascript");
+    assert_eq!(response.headers()[header::CONTENT_LENGTH], "3");
+    assert_eq!(response.headers()[header::ETAG], app_js.etag);
+    assert_eq!(
+        response.headers()[header::CACHE_CONTROL],
+        "public, max-age=31536000, immutable"
+    );
+    assert!(response.headers().get(header::CONTENT_DISPOSITION).is_none());
+    assert_eq!(&response.body()[..], b"app");
+
+    let response = brochure_pdf.into_http_response(&HeaderMap::new());
+    assert_eq!(
+        response.headers()[header::CONTENT_DISPOSITION],
+        "attachment; filename=\"brochure.pdf\""
+    );
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::IF_NONE_MATCH, HeaderValue::from_str(app_js.etag).unwrap());
+    let response = app_js.into_http_response(&headers);
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers()[header::ETAG], app_js.etag);
+    assert!(response.body().is_empty());
+
+    let response = StaticFile::not_found_http_response();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert!(response.headers().get(header::CONTENT_TYPE).is_none());
+    assert!(response.body().is_empty());
+}}
+"#
+            ),
+            &["http@1", "bytes"],
+        );
+
+        Config::new()
+            .embed(true)
+            .http(true)
+            .not_found_body("text/html", "<h1>Not here</h1>")
+            .codegen(&out_path, std::slice::from_ref(&asset_dir), &[])
+            .unwrap();
+        run_program_with_externs(
+            &format!(
+                r#"include!({out_path:?});
+
+use http::{{header, StatusCode}};
+
+fn main() {{
+    let response = StaticFile::not_found_http_response();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "text/html");
+    assert_eq!(response.headers()[header::CONTENT_LENGTH], "17");
+    assert_eq!(&response.body()[..], b"<h1>Not here</h1>");
+}}
+"#
+            ),
+            &["http@1", "bytes"],
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "rocket")]
+    fn test_rocket_responder() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        fs::write(asset_dir.join("my file.css"), b"body {}").unwrap();
+
+        Config::new()
+            .embed(true)
+            .etag(HashAlgorithm::Md5)
+            .rocket(true)
+            .codegen(&out_path, std::slice::from_ref(&asset_dir), &[])
+            .unwrap();
+
+        run_program_with_externs(
+            &format!(
+                r#"include!({out_path:?});
+
+use rocket::http::{{Header, Status}};
+use rocket::local::blocking::Client;
+
+fn main() {{
+    let client = Client::untracked(rocket::build().attach(StaticFile::fairing())).unwrap();
+
+    let response = client.get(app_js.name).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.headers().get_one("Content-Type"), Some("application/javascript"));
+    assert_eq!(response.headers().get_one("ETag"), Some(app_js.etag));
+    assert_eq!(
+        response.headers().get_one("Cache-Control"),
+        Some("public, max-age=31536000, immutable")
+    );
+    assert_eq!(response.into_bytes().unwrap(), b"app");
+
+    let response = client
+        .get(app_js.name)
+        .header(Header::new("If-None-Match", app_js.etag))
+        .dispatch();
+    assert_eq!(response.status(), Status::NotModified);
+
+    let response = client.get(my_file_css.name.replace(' ', "%20")).dispatch();
+    assert_eq!(response.into_bytes().unwrap(), b"body {{}}");
+    assert_eq!(client.get("/static/missing.js").dispatch().status(), Status::NotFound);
+}}
+"#
+            ),
+            &["rocket"],
+        );
+
+        Config::new()
+            .rocket(true)
+            .download("*.css")
+            .not_found_body("text/html", "<h1>Not here</h1>")
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+        run_program_with_externs(
+            &format!(
+                r#"include!({out_path:?});
+
+fn main() {{
+    let rocket = rocket::build().mount("/", vec![StaticFile::rocket_route()]);
+    let client = rocket::local::blocking::Client::untracked(rocket).unwrap();
+
+    let response = client.get(app_js.name).dispatch();
+    assert_eq!(response.status(), rocket::http::Status::Ok);
+    assert!(response.headers().get_one("ETag").is_none());
+    assert!(response.headers().get_one("Content-Disposition").is_none());
+    assert_eq!(response.into_bytes().unwrap(), b"app");
+
+    let response = client.get(my_file_css.name.replace(' ', "%20")).dispatch();
+    assert_eq!(
+        response.headers().get_one("Content-Disposition"),
+        Some("attachment; filename=\"my file.css\"")
+    );
+
+    let client = rocket::local::blocking::Client::untracked(
+        rocket::build().attach(StaticFile::fairing()),
+    )
+    .unwrap();
+    let response = client.get("/static/missing.js").dispatch();
+    assert_eq!(response.status(), rocket::http::Status::NotFound);
+    assert_eq!(response.headers().get_one("Content-Type"), Some("text/html"));
+    assert_eq!(response.into_bytes().unwrap(), b"<h1>Not here</h1>");
+}}
+"#
+            ),
+            &["rocket"],
+        );
+    }
+
+    #[test]
+    fn test_base_url_env() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let test_content = b"base url content";
+        fs::write(dir.path().join("app.js"), test_content).unwrap();
+
+        codegen(&out_path, &[dir.path().to_path_buf()], &[]).unwrap();
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(!generated.contains("fn url("));
+
+        Config::new()
+            .base_url_env("CACHEB_TEST_BASE_URL")
+            .codegen(&out_path, &[dir.path().to_path_buf()], &[])
+            .unwrap();
+
+        let hash = format!("{:x}", md5::compute(test_content));
+        run_generated(
+            &out_path,
+            &format!(
+                r#"
+    std::env::set_var("CACHEB_TEST_BASE_URL", "https://cdn.example.com");
+    assert_eq!(app_js.url(), "https://cdn.example.com/static/app-{hash}.js");
+    assert!(StaticFile::set_base_url("https://other.example.com").is_err());
+"#
+            ),
+        );
+        run_generated(
+            &out_path,
+            &format!(
+                r#"
+    StaticFile::set_base_url("https://assets.example.com/").unwrap();
+    assert_eq!(app_js.url(), "https://assets.example.com/static/app-{hash}.js");
+"#
+            ),
+        );
+    }
+
+    #[test]
+    fn test_serve_root() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        let fixture_dir = dir.path().join("fixture");
+        for root in [&asset_dir, &fixture_dir] {
+            fs::create_dir_all(root.join("app")).unwrap();
+        }
+        fs::write(asset_dir.join("app/main.js"), b"built").unwrap();
+        fs::write(fixture_dir.join("app/main.js"), b"fixture").unwrap();
+
+        Config::new()
+            .serve_root(true)
+            .strip_prefix("app")
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+
+        let fixture_dir = fixture_dir.to_str().unwrap();
+        run_generated(
+            &out_path,
+            &format!(
+                r#"
+    assert_eq!(main_js.rel_path, "app/main.js");
+    assert_eq!(main_js.read().unwrap(), b"built");
+
+    StaticFile::set_serve_root(Some({fixture_dir:?}.into()));
+    let read = std::thread::spawn(|| main_js.read().unwrap());
+    assert_eq!(read.join().unwrap(), b"fixture");
+    assert_eq!(main_js.path(), std::path::Path::new({fixture_dir:?}).join("app/main.js"));
+
+    StaticFile::set_serve_root(None);
+    assert_eq!(main_js.read().unwrap(), b"built");
+    assert_eq!(std::fs::read(main_js.path_in({fixture_dir:?}.as_ref())).unwrap(), b"fixture");
+"#
+            ),
+        );
+    }
+
+    #[test]
+    fn test_custom_hasher() {
+        /// Digests to the number of bytes fed in, as a big-endian `u32`.
+        struct LengthHasher(u32);
+
+        impl ContentHash for LengthHasher {
+            fn update(&mut self, data: &[u8]) {
+                self.0 += data.len() as u32;
+            }
+
+            fn finalize(self: Box<Self>) -> Vec<u8> {
+                self.0.to_be_bytes().to_vec()
+            }
+        }
+
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+        fs::write(dir.path().join("app.js"), b"twelve bytes").unwrap();
+
+        Config::new()
+            .hasher(|| Box::new(LengthHasher(0)))
+            .etag(HashAlgorithm::Md5)
+            .codegen(&out_path, &[dir.path().to_path_buf()], &[])
+            .unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains("name: \"/static/app-0000000c.js\""));
+        let md5 = format!("{:x}", md5::compute(b"twelve bytes"));
+        assert!(generated.contains(&format!("etag: \"\\\"{md5}\\\"\"")));
+    }
+
+    #[test]
+    fn test_get_or_404() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let errors_dir = dir.path().join("errors");
+        fs::create_dir(&errors_dir).unwrap();
+        fs::write(errors_dir.join("not-found.html"), b"<h1>Not found</h1>").unwrap();
+        fs::write(dir.path().join("app.js"), b"app").unwrap();
+
+        codegen(&out_path, &[dir.path().to_path_buf()], &[]).unwrap();
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(!generated.contains("get_or_404"));
+
+        Config::new()
+            .fallback(errors_dir.join("not-found.html"))
+            .codegen(&out_path, &[dir.path().to_path_buf()], &[])
+            .unwrap();
+
+        run_generated(
+            &out_path,
+            r#"
+    assert!(std::ptr::eq(StaticFile::get_or_404("/static/missing.js"), &errors::not_found_html));
+    assert!(std::ptr::eq(StaticFile::get_or_404(app_js.name), &app_js));
+"#,
+        );
+
+        let err = Config::new()
+            .fallback(dir.path().join("app.js"))
+            .codegen(&out_path, &[], &[])
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+
+        let err = Config::new()
+            .fallback(errors_dir.join("not-found.html"))
+            .cfg(&errors_dir, "feature = \"errors\"")
+            .codegen(&out_path, &[dir.path().to_path_buf()], &[])
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("not-found.html"));
+    }
+
+    #[test]
+    fn test_unchanged_output_is_not_rewritten() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+
+        codegen(&out_path, std::slice::from_ref(&asset_dir), &[]).unwrap();
+
+        let old_time = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1);
+        File::options()
+            .write(true)
+            .open(&out_path)
+            .unwrap()
+            .set_modified(old_time)
+            .unwrap();
+
+        codegen(&out_path, std::slice::from_ref(&asset_dir), &[]).unwrap();
+        assert_eq!(
+            fs::metadata(&out_path).unwrap().modified().unwrap(),
+            old_time
+        );
+
+        fs::write(asset_dir.join("app.js"), b"changed").unwrap();
+        codegen(&out_path, std::slice::from_ref(&asset_dir), &[]).unwrap();
+        assert_ne!(
+            fs::metadata(&out_path).unwrap().modified().unwrap(),
+            old_time
+        );
+    }
+
+    #[test]
+    fn test_report_changes() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        fs::write(asset_dir.join("favicon.ico"), b"favicon").unwrap();
+        fs::write(asset_dir.join("main.css"), b"main").unwrap();
+        let asset_dirs = [asset_dir.clone()];
+
+        let config = Config::new().no_hash("favicon.ico");
+        let first = config.codegen(&out_path, &asset_dirs, &[]).unwrap();
+        assert!(first.changes.is_none());
+
+        fs::write(asset_dir.join("app.js"), b"changed").unwrap();
+        fs::write(asset_dir.join("favicon.ico"), b"new favicon").unwrap();
+        fs::remove_file(asset_dir.join("main.css")).unwrap();
+        fs::write(asset_dir.join("new.js"), b"new").unwrap();
+        let second = config
+            .codegen_since(&out_path, &asset_dirs, &[], &first)
+            .unwrap();
+        let changes = second.changes.as_ref().unwrap();
+        assert_eq!(changes.changed, ["app.js", "favicon.ico"]);
+        assert_eq!(changes.added, ["new.js"]);
+        assert_eq!(changes.removed, ["main.css"]);
+
+        let third = config
+            .codegen_since(&out_path, &asset_dirs, &[], &second)
+            .unwrap();
+        assert!(third.changes.unwrap().is_empty());
+
+        // Runs don't share any state, so plain codegen still reports none
+        // and an older report still compares against its own run.
+        assert!(config
+            .codegen(&out_path, &asset_dirs, &[])
+            .unwrap()
+            .changes
+            .is_none());
+        let changes = config
+            .codegen_since(&out_path, &asset_dirs, &[], &first)
+            .unwrap()
+            .changes
+            .unwrap();
+        assert_eq!(changes.changed, ["app.js", "favicon.ico"]);
+    }
+
+    #[test]
+    fn test_routes() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let vendor_dir = dir.path().join("vendor");
+        fs::create_dir(&vendor_dir).unwrap();
+        fs::write(vendor_dir.join("script.js"), b"script").unwrap();
+        fs::write(dir.path().join("main.css"), b"main").unwrap();
+
+        Config::new()
+            .routes(true)
+            .codegen(&out_path, &[dir.path().to_path_buf()], &[])
+            .unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains("pub const MAIN_CSS_ROUTE: &str = \"/static/main-"));
+        assert!(
+            generated.contains("    pub const SCRIPT_JS_ROUTE: &str = \"/static/vendor/script-")
+        );
+
+        run_generated(
+            &out_path,
+            r#"
+    assert_eq!(MAIN_CSS_ROUTE, main_css.name);
+    assert_eq!(vendor::SCRIPT_JS_ROUTE, vendor::script_js.name);
+    let routes: Vec<_> = StaticFile::routes().collect();
+    assert_eq!(routes.len(), 2);
+    for (method, path, file) in routes {
+        assert_eq!(method, "GET");
+        assert_eq!(path, file.name);
+    }
+"#,
+        );
+    }
+
+    #[test]
+    fn test_sanitized_idents() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("vendor libs")).unwrap();
+        fs::write(asset_dir.join("my file (1).css"), b"file").unwrap();
+        fs::write(asset_dir.join("c++ notes.txt"), b"notes").unwrap();
+        fs::write(asset_dir.join("vendor libs/@scope+pkg.js"), b"pkg").unwrap();
+        fs::write(asset_dir.join("2 + 2.txt"), b"four").unwrap();
+
+        Config::new().codegen(&out_path, &[asset_dir], &[]).unwrap();
+
+        run_generated(
+            &out_path,
+            r#"
+    assert!(my_file_1_css.name.contains("my file (1)"));
+    assert!(c_notes_txt.name.contains("c++ notes"));
+    assert!(vendor_libs::_scope_pkg_js.name.contains("@scope+pkg"));
+    assert!(_2_2_txt.name.contains("2 + 2"));
+"#,
+        );
+    }
+
+    #[test]
+    fn test_ident_strategy() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        fs::write(asset_dir.join("a.js"), b"a").unwrap();
+        fs::write(asset_dir.join("a.css"), b"a").unwrap();
+        fs::write(asset_dir.join("jquery.min.js"), b"jquery").unwrap();
+        fs::write(asset_dir.join("type.js"), b"type").unwrap();
+
+        Config::new()
+            .ident_strategy(IdentStrategy::FileStem)
+            .codegen(&out_path, std::slice::from_ref(&asset_dir), &[])
+            .unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains("pub static a_js:"));
+        assert!(generated.contains("pub static a_css:"));
+        assert!(generated.contains("pub static jquery_min:"));
+        assert!(generated.contains("pub static type_:"));
+        assert!(!generated.contains("pub static a:"));
+
+        run_generated(&out_path, "assert_eq!(STATICS.len(), 4);");
+
+        Config::new()
+            .ident_strategy(IdentStrategy::Custom(|name| format!("asset {}", name)))
+            .codegen(&out_path, std::slice::from_ref(&asset_dir), &[])
+            .unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains("pub static asset_jquery_min_js:"));
+        run_generated(&out_path, "assert_eq!(STATICS.len(), 4);");
+    }
+
+    #[test]
+    fn test_generated_compiles() {
+        let files = [
+            "app.js",
+            "index.html",
+            "a/b/c/deep.css",
+            "a/b/index.html",
+            "root/app.js",
+            "2x/icon.png",
+            "type/fn.js",
+            "my dir/my file.css",
+            "odd \"name\"\\.js",
+            "caf\u{e9}/men\u{fc}.svg",
+            "jquery.min.js",
+            "hero.jpg",
+            "hero.webp",
+            "jquery.min.js.map",
+            "-dash.js",
+            "_",
+            "self",
+            "LICENSE",
+            "name",
+            "f",
+            "STATICS",
+            "StaticFile/app.js",
+            "empty/",
+            "nested/empty/",
+        ];
+
+        assert_compiles(&Config::new(), &files);
+        assert_compiles(&Config::new().flat(true), &files);
+        assert_compiles(&Config::new().lazy_lookup(true), &files);
+        assert_compiles(
+            &Config::new()
+                .match_lookup(true)
+                .resolve(true)
+                .url_macro(true)
+                .categories(true)
+                .dir_hashes(true)
+                .asset_enum(true),
+            &files,
+        );
+        assert_compiles(&Config::new(), &[]);
+        assert_compiles(&Config::new(), &["empty/"]);
+        assert_compiles(
+            &Config::new()
+                .index_files(true)
+                .const_refs(true)
+                .routes(true)
+                .kinds(true)
+                .embed(true)
+                .etag(HashAlgorithm::Sha256)
+                .alternates(true)
+                .source_maps(true)
+                .rewrite_source_map_urls(true)
+                .generated_at(true)
+                .base_url_env("CACHEB_TEST_BASE_URL")
+                .public_url_prefix("/public/")
+                .download("*.js")
+                .hoist_bytes(true)
+                .not_found_body("text/html", "<h1>\"Gone\"</h1>")
+                .serve_root(true)
+                .meta("*.js", "defer", "true")
+                .ident_strategy(IdentStrategy::FileStem),
+            &files,
+        );
+    }
+
+    #[test]
+    fn test_asset_enum() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("vendor")).unwrap();
+        fs::create_dir_all(asset_dir.join("a")).unwrap();
+        fs::create_dir_all(asset_dir.join("a_b")).unwrap();
+        fs::write(asset_dir.join("root.css"), b"root").unwrap();
+        fs::write(asset_dir.join("vendor/script.js"), b"script").unwrap();
+        fs::write(asset_dir.join("a/b_c.js"), b"a").unwrap();
+        fs::write(asset_dir.join("a_b/c.js"), b"a_b").unwrap();
+
+        Config::new()
+            .asset_enum(true)
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+
+        run_generated(
+            &out_path,
+            r#"
+    assert_eq!(Asset::RootCss.file(), &root_css);
+    assert_eq!(Asset::VendorScriptJs.file(), &vendor::script_js);
+    assert_eq!(Asset::ABCJs.file(), &a::b_c_js);
+    assert_eq!(Asset::ABCJs2.file(), &a_b::c_js);
+
+    for asset in [Asset::RootCss, Asset::VendorScriptJs, Asset::ABCJs, Asset::ABCJs2] {
+        let precached = match asset {
+            Asset::RootCss | Asset::VendorScriptJs => true,
+            Asset::ABCJs | Asset::ABCJs2 => false,
+        };
+        assert_eq!(precached, !asset.file().name.contains("/a"));
+    }
+"#,
+        );
+    }
+
+    #[test]
+    fn test_lint_allows() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("app")).unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        fs::write(asset_dir.join("app/main.js"), b"main").unwrap();
+
+        Config::new()
+            .kinds(true)
+            .generated_at(true)
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+        let generated = fs::read_to_string(&out_path).unwrap();
+
+        // Every top-level item, and only those, carries the allows.
+        let lines = generated.lines().collect::<Vec<_>>();
+        let top_level_items = lines
+            .iter()
+            .filter(|line| {
+                ["pub ", "static ", "mod "]
+                    .iter()
+                    .any(|item| line.starts_with(item))
+            })
+            .count();
+        assert_eq!(top_level_items, 7);
+        assert_eq!(generated.matches(ALLOW_LINTS).count(), top_level_items);
+        for (i, line) in lines.iter().enumerate() {
+            if line.starts_with(ALLOW_LINTS) {
+                assert!(lines[i + 1..]
+                    .iter()
+                    .find(|line| !line.starts_with('#'))
+                    .is_some_and(|item| !item.starts_with(' ')));
+            }
+        }
+
+        run_generated(&out_path, "assert_eq!(STATICS.len(), 2);");
+    }
+
+    #[test]
+    fn test_ident_collision() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        fs::write(dir.path().join("a-b.js"), b"dash").unwrap();
+        fs::write(dir.path().join("a.b.js"), b"dot").unwrap();
+
+        let err = codegen(&out_path, &[dir.path().to_path_buf()], &[]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("`a_b_js`"));
+    }
+
+    #[test]
+    fn test_root_files_and_extra_files() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
 
-    output.push_str(
-        r#"// This file was generated by `cacheb`. Do not modify.
+        let asset_dir = dir.path().join("assets");
+        let extra_dir = dir.path().join("extra");
+        fs::create_dir(&asset_dir).unwrap();
+        fs::create_dir(&extra_dir).unwrap();
+        fs::write(asset_dir.join("app.js"), b"asset").unwrap();
+        fs::write(extra_dir.join("app.css"), b"extra").unwrap();
+        fs::write(extra_dir.join("app.js"), b"clash").unwrap();
+        let asset_dirs = [asset_dir];
 
-#[derive(Debug)]
-pub struct StaticFile {
-    pub file_name: &'static str,
-    pub name: &'static str,
-    pub mime: &'static str,
-}
+        Config::new()
+            .codegen(&out_path, &asset_dirs, &[extra_dir.join("app.css")])
+            .unwrap();
+        let js = format!("/static/app-{:x}.js", md5::compute(b"asset"));
+        let css = format!("/static/app-{:x}.css", md5::compute(b"extra"));
+        run_generated(
+            &out_path,
+            &format!(
+                r#"assert_eq!(STATICS.len(), 2);
+                assert_eq!(StaticFile::get("{js}"), Some(&app_js));
+                assert_eq!(StaticFile::get("{css}"), Some(&app_css));"#
+            ),
+        );
+
+        let err = Config::new()
+            .codegen(&out_path, &asset_dirs, &[extra_dir.join("app.js")])
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("`app_js`"));
+    }
+
+    #[test]
+    fn test_logical_path() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let vendor_dir = dir.path().join("node_modules/foo/dist");
+        fs::create_dir_all(&vendor_dir).unwrap();
+        fs::write(vendor_dir.join("foo.min.js"), b"foo").unwrap();
+        fs::write(vendor_dir.join("foo.css"), b"foo").unwrap();
+        fs::write(vendor_dir.join("bar.js"), b"bar").unwrap();
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("app")).unwrap();
+        fs::write(asset_dir.join("app/main.js"), b"main").unwrap();
+
+        let extra_files = [
+            vendor_dir.join("foo.min.js"),
+            vendor_dir.join("foo.css"),
+            vendor_dir.join("bar.js"),
+        ];
+        let config = Config::new()
+            .logical_path(vendor_dir.join("foo.min.js"), "libs/foo.js")
+            .logical_path(vendor_dir.join("foo.css"), "libs/css/foo.css");
+        config
+            .codegen(&out_path, std::slice::from_ref(&asset_dir), &extra_files)
+            .unwrap();
+
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert_eq!(generated.matches("pub mod libs {").count(), 1);
+        run_generated(
+            &out_path,
+            &format!(
+                r#"
+    assert_eq!(libs::foo_js.name, "/static/libs/foo-{:x}.js");
+    assert_eq!(libs::css::foo_css.name, "/static/libs/css/foo-{:x}.css");
+    assert_eq!(bar_js.name, "/static/bar-{:x}.js");
+    assert_eq!(STATICS.len(), 4);
 "#,
-    );
+                md5::compute(b"foo"),
+                md5::compute(b"foo"),
+                md5::compute(b"bar")
+            ),
+        );
+        let manifest = config
+            .manifest(std::slice::from_ref(&asset_dir), &extra_files)
+            .unwrap();
+        assert!(manifest.contains("\"libs/foo.js\""));
 
-    for asset_dir in asset_dirs {
-        process_directory(
-            asset_dir,
-            asset_dir,
-            &mut output,
-            &mut static_files,
-            &mut module_map,
-            0,
-        )?;
-    }
-
-    for file_path in extra_files {
-        if let Some(parent) = file_path.parent() {
-            process_file(
-                file_path,
-                parent,
-                &mut output,
-                &mut static_files,
-                &mut module_map,
-                0,
-            )?;
-        } else {
-            process_file(
-                file_path,
-                Path::new(""),
-                &mut output,
-                &mut static_files,
-                &mut module_map,
-                0,
-            )?;
-        }
+        let err = Config::new()
+            .logical_path(vendor_dir.join("bar.js"), "app/bar.js")
+            .codegen(&out_path, std::slice::from_ref(&asset_dir), &extra_files)
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("module `app`"));
+
+        let err = Config::new()
+            .logical_path(vendor_dir.join("bar.js"), "../bar.js")
+            .codegen(&out_path, &[asset_dir], &extra_files)
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
     }
 
-    output.push_str(
-        r#"
-#[allow(dead_code)]
-impl StaticFile {
-    /// Get a single `StaticFile` by name, if it exists.
-    #[must_use]
-    pub fn get(name: &str) -> Option<&'static Self> {
-        if let Some(pos) = STATICS.iter().position(|&s| name == s.name) {
-            Some(STATICS[pos])
-        } else {
-            None
+    #[test]
+    fn test_sorted_declarations() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        for name in ["zeta", "alpha", "Mid", "beta"] {
+            fs::create_dir_all(asset_dir.join(name)).unwrap();
+            fs::write(asset_dir.join(name).join("b.js"), name).unwrap();
+            fs::write(asset_dir.join(name).join("a.js"), name).unwrap();
         }
+
+        codegen(&out_path, &[asset_dir], &[]).unwrap();
+        let generated = fs::read_to_string(&out_path).unwrap();
+
+        let positions = [
+            "pub mod Mid",
+            "pub mod alpha",
+            "pub mod beta",
+            "pub mod zeta",
+        ]
+        .map(|module| generated.find(module).unwrap());
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let a = generated.find("pub static a_js").unwrap();
+        let b = generated.find("pub static b_js").unwrap();
+        assert!(a < b);
+        assert!(generated.contains("&Mid::a_js,\n    &Mid::b_js,\n    &alpha::a_js"));
     }
-}
 
-impl std::fmt::Display for StaticFile {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.name)
+    #[test]
+    fn test_include_hidden() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join(".well-known")).unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        fs::write(asset_dir.join(".hidden"), b"hidden").unwrap();
+        fs::write(asset_dir.join(".well-known/security.txt"), b"security").unwrap();
+
+        codegen(&out_path, std::slice::from_ref(&asset_dir), &[]).unwrap();
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains("pub static app_js"));
+        assert!(!generated.contains(".hidden"));
+        assert!(!generated.contains("well_known"));
+
+        Config::new()
+            .include_hidden(true)
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains("pub static _hidden"));
+        assert!(generated.contains("pub mod _well_known"));
+        assert!(generated.contains("/static/.well-known/security-"));
     }
-}
-"#,
-    );
 
-    let statics_array = static_files
-        .iter()
-        .map(|item| format!("\n    {}", item))
-        .collect::<Vec<_>>()
-        .join(",");
+    #[test]
+    fn test_by_kind() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
 
-    output.push_str(&format!(
-        "\nstatic STATICS: &[&StaticFile] = &[{}\n];\n",
-        statics_array
-    ));
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("styles")).unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        fs::write(asset_dir.join("logo.png"), b"logo").unwrap();
+        fs::write(asset_dir.join("main.css"), b"main").unwrap();
+        fs::write(asset_dir.join("styles/print.css"), b"print").unwrap();
 
-    let mut out_file = File::create(out_path)?;
-    out_file.write_all(output.as_bytes())?;
+        codegen(&out_path, std::slice::from_ref(&asset_dir), &[]).unwrap();
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(!generated.contains("StaticKind"));
 
-    Ok(())
-}
+        Config::new()
+            .kinds(true)
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
 
-fn process_directory(
-    dir: &Path,
-    base_dir: &Path,
-    output: &mut String,
-    static_files: &mut Vec<String>,
-    module_map: &mut HashMap<String, Vec<String>>,
-    indent_level: usize,
-) -> std::io::Result<()> {
-    let rel_path = dir.strip_prefix(base_dir).unwrap_or(dir);
-    let dir_module_path = get_module_path(rel_path);
+        run_generated(
+            &out_path,
+            r#"
+    let styles: Vec<_> = StaticFile::by_kind(StaticKind::Style).collect();
+    assert_eq!(styles, [&main_css, &styles::print_css]);
+    assert_eq!(app_js.kind(), StaticKind::Script);
+    assert_eq!(logo_png.kind(), StaticKind::Image);
+    assert_eq!(StaticFile::all().count(), 4);
+    assert_eq!(StaticFile::by_kind(StaticKind::Font).count(), 0);
+"#,
+        );
+    }
 
-    let create_module = !rel_path.as_os_str().is_empty();
+    #[test]
+    fn test_categories() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
 
-    if create_module {
-        let module_name = rel_path
-            .file_name()
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .replace(['-', '.'], "_");
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("img")).unwrap();
+        fs::create_dir_all(asset_dir.join("admin/img")).unwrap();
+        fs::write(asset_dir.join("img/logo.png"), b"logo").unwrap();
+        fs::write(asset_dir.join("admin/img/logo.png"), b"admin logo").unwrap();
+        fs::write(asset_dir.join("admin/main.css"), b"main").unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        fs::write(asset_dir.join("readme.txt"), b"readme").unwrap();
 
-        let indent = "    ".repeat(indent_level);
-        output.push_str(&format!("\n{}pub mod {} {{\n", indent, module_name));
-        output.push_str(&format!("{}    use super::StaticFile;\n", indent));
+        Config::new()
+            .categories(true)
+            .codegen(&out_path, std::slice::from_ref(&asset_dir), &[])
+            .unwrap();
 
-        if !module_map.contains_key(&dir_module_path) {
-            module_map.insert(dir_module_path.clone(), Vec::new());
-        }
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains("pub use super::admin::img::logo_png;"));
+        assert!(generated.contains("pub use super::img::logo_png as logo_png_2;"));
+        run_generated(
+            &out_path,
+            r#"
+    assert!(std::ptr::eq(&images::logo_png, &admin::img::logo_png));
+    assert!(std::ptr::eq(&images::logo_png_2, &img::logo_png));
+    assert!(std::ptr::eq(&styles::main_css, &admin::main_css));
+    assert!(std::ptr::eq(&scripts::app_js, &app_js));
+"#,
+        );
+
+        fs::create_dir(asset_dir.join("images")).unwrap();
+        fs::write(asset_dir.join("images/hero.jpg"), b"hero").unwrap();
+        let err = Config::new()
+            .categories(true)
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("directory module `images`"));
     }
 
-    let next_indent = if create_module {
-        indent_level + 1
-    } else {
-        indent_level
-    };
+    #[test]
+    fn test_dir_hashes() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
 
-    for entry in fs::read_dir(dir)? {
-        let entry = entry?;
-        let path = entry.path();
-
-        if path.is_file() {
-            process_file(
-                &path,
-                base_dir,
-                output,
-                static_files,
-                module_map,
-                next_indent,
-            )?;
-        }
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("docs/guide")).unwrap();
+        fs::create_dir_all(asset_dir.join("other")).unwrap();
+        fs::write(asset_dir.join("docs/index.html"), b"index").unwrap();
+        fs::write(asset_dir.join("docs/guide/intro.html"), b"intro").unwrap();
+        fs::write(asset_dir.join("other/app.js"), b"app").unwrap();
+
+        let dir_hashes = || {
+            Config::new()
+                .dir_hashes(true)
+                .codegen(&out_path, std::slice::from_ref(&asset_dir), &[])
+                .unwrap();
+            // Modules close innermost first: docs::guide, docs, other.
+            fs::read_to_string(&out_path)
+                .unwrap()
+                .lines()
+                .filter_map(|line| line.trim().strip_prefix("pub const DIR_HASH: &str = "))
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+
+        let before = dir_hashes();
+        assert_eq!(before.len(), 3);
+        assert_eq!(dir_hashes(), before);
+        run_generated(
+            &out_path,
+            "assert_eq!(docs::DIR_HASH.len(), 32);\n    assert_ne!(docs::DIR_HASH, docs::guide::DIR_HASH);",
+        );
+
+        fs::write(asset_dir.join("docs/guide/intro.html"), b"changed").unwrap();
+        let after = dir_hashes();
+        assert_ne!(after[0], before[0]);
+        assert_ne!(after[1], before[1]);
+        assert_eq!(after[2], before[2]);
+
+        // Renaming a file changes the hash even though its content doesn't.
+        fs::rename(
+            asset_dir.join("other/app.js"),
+            asset_dir.join("other/main.js"),
+        )
+        .unwrap();
+        assert_ne!(dir_hashes()[2], after[2]);
     }
 
-    for entry in fs::read_dir(dir)? {
-        let entry = entry?;
-        let path = entry.path();
+    #[test]
+    fn test_alternates() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
 
-        if path.is_dir() {
-            process_directory(
-                &path,
-                base_dir,
-                output,
-                static_files,
-                module_map,
-                next_indent,
-            )?;
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("icons")).unwrap();
+        for file in [
+            "hero.jpg",
+            "hero.webp",
+            "hero.AVIF",
+            "logo.png",
+            "icons/hero.webp",
+        ] {
+            fs::write(asset_dir.join(file), file).unwrap();
         }
-    }
 
-    if create_module {
-        let indent = "    ".repeat(indent_level);
-        output.push_str(&format!("{}}}\n", indent));
+        Config::new()
+            .alternates(true)
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
+
+        run_generated(
+            &out_path,
+            r#"assert_eq!(hero_jpg.alternates, [&hero_AVIF, &hero_webp]);
+            assert!(hero_webp.alternates.is_empty());
+            assert!(hero_AVIF.alternates.is_empty());
+            assert!(logo_png.alternates.is_empty());
+            assert_eq!(STATICS.len(), 5);
+            assert_eq!(hero_AVIF.mime, "image/avif");"#,
+        );
     }
 
-    Ok(())
-}
+    #[test]
+    fn test_source_maps() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
 
-fn process_file(
-    path: &Path,
-    base_dir: &Path,
-    output: &mut String,
-    static_files: &mut Vec<String>,
-    module_map: &mut HashMap<String, Vec<String>>,
-    indent_level: usize,
-) -> std::io::Result<()> {
-    let full_path = fs::canonicalize(&path)?;
-    let file_name = full_path.to_str().unwrap();
-
-    let hash = calculate_hash(&path)?;
-
-    let var_name = path
-        .file_name()
-        .unwrap()
-        .to_str()
-        .unwrap()
-        .replace(['/', '.', '-'], "_");
-
-    let file_stem = path.file_stem().unwrap().to_str().unwrap();
-    let extension = path.extension().unwrap().to_str().unwrap();
-
-    let rel_path = path.strip_prefix(base_dir).unwrap_or(path);
-    let rel_dir = rel_path.parent().unwrap_or(Path::new(""));
-    let rel_dir_str = rel_dir.to_str().unwrap().replace('\\', "/");
-
-    let url_path = if rel_dir_str.is_empty() {
-        format!("/static/{file_stem}-{hash}.{extension}")
-    } else {
-        format!("/static/{rel_dir_str}/{file_stem}-{hash}.{extension}")
-    };
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        let app = b"app();\n//# sourceMappingURL=app.js.map\n";
+        fs::write(asset_dir.join("app.js"), app).unwrap();
+        fs::write(asset_dir.join("app.js.map"), b"{}").unwrap();
+        fs::write(
+            asset_dir.join("main.css"),
+            b"a{}\n/*# sourceMappingURL=main.css.map */",
+        )
+        .unwrap();
+        fs::write(asset_dir.join("main.css.map"), b"{ }").unwrap();
+        fs::write(asset_dir.join("lone.js"), b"lone").unwrap();
+        let asset_dirs = [asset_dir];
 
-    let mime_type = mime_type_from_extension(extension);
+        Config::new()
+            .source_maps(true)
+            .codegen(&out_path, &asset_dirs, &[])
+            .unwrap();
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(generated.contains(&format!("app-{:x}.js\"", md5::compute(app))));
+        run_generated(
+            &out_path,
+            r#"assert_eq!(app_js.source_map, Some(&app_js_map));
+            assert_eq!(main_css.source_map, Some(&main_css_map));
+            assert_eq!(lone_js.source_map, None);
+            assert_eq!(app_js_map.source_map, None);
+            assert_eq!(STATICS.len(), 5);"#,
+        );
 
-    let module_path = if rel_dir.to_str().unwrap().is_empty() {
-        "root".to_string()
-    } else {
-        get_module_path(rel_dir)
-    };
+        Config::new()
+            .source_maps(true)
+            .rewrite_source_map_urls(true)
+            .embed(true)
+            .codegen(&out_path, &asset_dirs, &[])
+            .unwrap();
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(!generated.contains(&format!("app-{:x}.js\"", md5::compute(app))));
+        run_generated(
+            &out_path,
+            r#"assert_eq!(
+                app_js.content,
+                format!("app();\n//# sourceMappingURL={}\n", app_js_map.name).as_bytes()
+            );
+            assert_eq!(
+                main_css.content,
+                format!("a{{}}\n/*# sourceMappingURL={} */", main_css_map.name).as_bytes()
+            );
+            assert_eq!(lone_js.content, b"lone");"#,
+        );
 
-    let indent = "    ".repeat(indent_level);
+        let err = Config::new()
+            .source_maps(true)
+            .rewrite_source_map_urls(true)
+            .codegen(&out_path, &asset_dirs, &[])
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
 
-    let file_code = format!(
-        r#"
-{indent}/// From "{file_name}"
-{indent}#[allow(non_upper_case_globals)]
-{indent}pub static {var_name}: StaticFile = StaticFile {{
-{indent}    file_name: "{file_name}",
-{indent}    name: "{url_path}",
-{indent}    mime: "{mime_type}",
-{indent}}};
-"#,
-    );
+    #[test]
+    fn test_codegen_to_writer() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
 
-    output.push_str(&file_code);
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("app/vendor/lib")).unwrap();
+        fs::create_dir_all(asset_dir.join("images")).unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        fs::write(asset_dir.join("app/main.js"), b"main").unwrap();
+        fs::write(asset_dir.join("app/vendor/lib/dep.js"), b"dep").unwrap();
+        fs::write(asset_dir.join("images/logo.png"), b"logo").unwrap();
 
-    if module_path == "root" {
-        static_files.push(format!("&{}", var_name));
-    } else {
-        let module_parts: Vec<&str> = module_path.split('/').collect();
-        let qualified_path = if module_parts.len() > 1 {
-            let mut parts = Vec::new();
-            for part in &module_parts {
-                if !part.is_empty() {
-                    parts.push(*part);
-                }
+        let config = Config::new().routes(true);
+        let mut written = Vec::new();
+        config
+            .codegen_to_writer(&mut written, std::slice::from_ref(&asset_dir), &[])
+            .unwrap();
+
+        config
+            .codegen(&out_path, std::slice::from_ref(&asset_dir), &[])
+            .unwrap();
+        assert_eq!(written, fs::read(&out_path).unwrap());
+
+        // Each directory is read once, with its entries sorted, so a second
+        // run over the same nested tree writes the same bytes.
+        let mut rewritten = Vec::new();
+        config
+            .codegen_to_writer(&mut rewritten, &[asset_dir], &[])
+            .unwrap();
+        assert_eq!(rewritten, written);
+        let written = String::from_utf8(written).unwrap();
+        assert!(written.contains("pub mod vendor {"));
+        assert!(written.contains("pub mod lib {"));
+
+        // The header and statics are written during the walk, the impls
+        // after it.
+        struct Sections(Vec<Vec<u8>>);
+        impl Write for Sections {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.push(buf.to_vec());
+                Ok(buf.len())
             }
-            format!("&{}::{}", parts.join("::"), var_name)
-        } else {
-            format!("&{}::{}", module_path, var_name)
-        };
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let mut sections = Sections(Vec::new());
+        config
+            .codegen_to_writer(&mut sections, &[dir.path().join("assets")], &[])
+            .unwrap();
+        let sections = sections
+            .0
+            .into_iter()
+            .map(|section| String::from_utf8(section).unwrap())
+            .collect::<Vec<_>>();
+        assert!(sections[0].starts_with("// @generated by cacheb"));
+        assert!(sections[0].contains("pub struct StaticFile {"));
+        assert!(!sections[0].contains("pub static"));
+        let impls = sections
+            .iter()
+            .position(|section| section.contains("mod static_file_impls"))
+            .unwrap();
+        assert!(
+            sections[..impls]
+                .iter()
+                .filter(|section| section.contains("pub static"))
+                .count()
+                >= 4
+        );
+        assert_eq!(sections.concat(), written);
+    }
 
-        static_files.push(qualified_path);
+    #[test]
+    fn test_tidy_sections() {
+        let code = "\n\nstruct A;\n\n\n   \nmod b {\r\n    fn c() {}\n\n}\n\n\n";
+        let tidy = |sections: &[&str]| {
+            let mut written = Vec::new();
+            let mut out = Tidy::new(&mut written);
+            for section in sections {
+                out.write(section).unwrap();
+            }
+            out.finish().unwrap();
+            String::from_utf8(written).unwrap()
+        };
 
-        if let Some(vars) = module_map.get_mut(&module_path) {
-            vars.push(var_name);
+        let expected = "struct A;\n\nmod b {\n    fn c() {}\n\n}\n";
+        assert_eq!(tidy(&[code]), expected);
+        // However the code is split into sections, even mid-line.
+        for i in 0..=code.len() {
+            assert_eq!(tidy(&[&code[..i], &code[i..]]), expected, "split at {i}");
         }
+        assert_eq!(
+            tidy(&code.split_inclusive('\n').collect::<Vec<_>>()),
+            expected
+        );
+        assert_eq!(tidy(&["struct A;"]), "struct A;\n");
+        assert_eq!(tidy(&["\n", " \n"]), "");
     }
 
-    Ok(())
-}
+    #[test]
+    fn test_warn_size() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
 
-fn get_module_path(path: &Path) -> String {
-    path.to_str()
-        .unwrap()
-        .replace('\\', "/")
-        .replace(['.', '-'], "_")
-}
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        fs::write(asset_dir.join("small.js"), [0; 10]).unwrap();
+        fs::write(asset_dir.join("exact.js"), [0; 100]).unwrap();
+        fs::write(asset_dir.join("video.mp4"), [0; 101]).unwrap();
 
-fn calculate_hash(path: &Path) -> std::io::Result<String> {
-    let mut file = File::open(path)?;
-    let mut buffer = Vec::new();
+        let report = codegen(&out_path, std::slice::from_ref(&asset_dir), &[]).unwrap();
+        assert!(report.large_files.is_empty());
 
-    file.read_to_end(&mut buffer)?;
+        let report = Config::new()
+            .warn_size(100)
+            .codegen(&out_path, std::slice::from_ref(&asset_dir), &[])
+            .unwrap();
+        assert_eq!(report.large_files, [(asset_dir.join("video.mp4"), 101)]);
+    }
 
-    let hash = md5::compute(&buffer);
-    Ok(format!("{:x}", hash))
-}
+    #[test]
+    fn test_manifest() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+        let manifest_path = dir.path().join("manifest.json");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("vendor")).unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        fs::write(asset_dir.join("vendor/lib.js"), b"lib").unwrap();
+        let extra_file = dir.path().join("robots.txt");
+        fs::write(&extra_file, b"robots").unwrap();
+
+        let config = Config::new();
+        let asset_dirs = [asset_dir];
+        let extra_files = [extra_file];
+
+        config
+            .write_manifest(&manifest_path, &asset_dirs, &extra_files)
+            .unwrap();
+        assert!(!out_path.exists());
+
+        let manifest = fs::read_to_string(&manifest_path).unwrap();
+        let app = format!("/static/app-{:x}.js", md5::compute(b"app"));
+        let lib = format!("/static/vendor/lib-{:x}.js", md5::compute(b"lib"));
+        let robots = format!("/static/robots-{:x}.txt", md5::compute(b"robots"));
+        assert_eq!(
+            manifest,
+            format!(
+                "{{\n  \"app.js\": \"{app}\",\n  \"robots.txt\": \"{robots}\",\n  \"vendor/lib.js\": \"{lib}\"\n}}\n"
+            )
+        );
+
+        config
+            .codegen(&out_path, &asset_dirs, &extra_files)
+            .unwrap();
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert_eq!(generated.matches(" name: \"").count(), 3);
+        for url in [app, lib, robots] {
+            assert!(generated.contains(&format!(" name: \"{url}\"")));
+        }
+        let (_, full) = generate(&config, &asset_dirs, &extra_files, None).unwrap();
+        assert_eq!(full.manifest.to_json(), manifest);
 
-fn mime_type_from_extension(extension: &str) -> &'static str {
-    match extension {
-        "svg" => "image/svg+xml",
-        "png" => "image/png",
-        "jpg" | "jpeg" => "image/jpeg",
-        "webp" => "image/webp",
-        "css" => "text/css",
-        "js" => "application/javascript",
-        "wasm" => "application/wasm",
-        _ => "application/octet-stream",
+        assert_eq!(config.manifest(&[], &[]).unwrap(), "{}\n");
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs;
-    use std::io::Write;
-    use tempfile::{tempdir, NamedTempFile};
+    #[test]
+    fn test_manifest_reproducible() {
+        let dir = tempdir().unwrap();
+        let files: [(&str, &[u8]); 4] = [
+            ("zebra.js", b"zebra"),
+            ("app/main.js", b"main"),
+            ("Beta.css", b"beta"),
+            ("app/alpha.css", b"alpha"),
+        ];
 
-    fn create_temp_file(content: &[u8], extension: &str) -> (NamedTempFile, PathBuf) {
-        let mut file = NamedTempFile::new().unwrap();
-        file.write_all(content).unwrap();
-        let path = file.path().to_path_buf();
+        // The same files, created in opposite orders in two directories.
+        let manifests = [false, true].map(|reverse| {
+            let asset_dir = dir.path().join(format!("assets_{reverse}"));
+            fs::create_dir_all(asset_dir.join("app")).unwrap();
+            let mut files = files.to_vec();
+            if reverse {
+                files.reverse();
+            }
+            for (path, content) in files {
+                fs::write(asset_dir.join(path), content).unwrap();
+            }
 
-        let new_path = path.with_extension(extension);
-        fs::rename(&path, &new_path).unwrap();
+            let manifest_path = dir.path().join(format!("manifest_{reverse}.json"));
+            Config::new()
+                .manifest_sizes(true)
+                .write_manifest(&manifest_path, &[asset_dir], &[])
+                .unwrap();
+            fs::read(&manifest_path).unwrap()
+        });
 
-        (file, new_path)
+        assert_eq!(manifests[0], manifests[1]);
+        let manifest = String::from_utf8(manifests[0].clone()).unwrap();
+        let keys = manifest
+            .lines()
+            .filter_map(|line| line.strip_prefix("  \""))
+            .map(|line| &line[..line.find('"').unwrap()])
+            .collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            ["Beta.css", "app/alpha.css", "app/main.js", "zebra.js"]
+        );
     }
 
     #[test]
-    fn test_basic_file_processing() {
+    #[cfg(all(feature = "gzip", feature = "brotli"))]
+    fn test_manifest_sizes() {
         let dir = tempdir().unwrap();
-        let out_path = dir.path().join("static_gen.rs");
+        let manifest_path = dir.path().join("manifest.json");
 
-        let test_content = b"test content";
-        let (_file, file_path) = create_temp_file(test_content, "js");
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        let script = "console.log('compressible');\n".repeat(100);
+        fs::write(asset_dir.join("app.js"), &script).unwrap();
+        fs::write(asset_dir.join("logo.png"), b"\x89PNG").unwrap();
+        let asset_dirs = [asset_dir.clone()];
+
+        let config = Config::new().gzip(true).brotli(true).manifest_sizes(true);
+        let json = config.manifest(&asset_dirs, &[]).unwrap();
+        let (_, full) = generate(&config, &asset_dirs, &[], None).unwrap();
+        assert_eq!(full.manifest.to_json(), json);
+        let manifest = Manifest::from_json(&json).unwrap();
+        let gzip = manifest.size("app.js", "gzip").unwrap();
+        let brotli = manifest.size("app.js", "br").unwrap();
+        assert_eq!(
+            manifest.size("app.js", "identity"),
+            Some(script.len() as u64)
+        );
+        assert!(gzip > 0 && gzip < script.len() as u64);
+        assert!(brotli > 0 && brotli < script.len() as u64);
+        assert_eq!(manifest.size("logo.png", "identity"), Some(4));
+        assert_eq!(manifest.size("logo.png", "gzip"), None);
+        assert_eq!(manifest.size("logo.png", "br"), None);
 
-        codegen(&out_path, &[], &[file_path.clone()]).unwrap();
+        // Without precompression, a changed file's size is patched in.
+        let config = Config::new().manifest_sizes(true);
+        config
+            .write_manifest(&manifest_path, &asset_dirs, &[])
+            .unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        config
+            .write_manifest_changed(&manifest_path, &asset_dirs, &[], &asset_dir.join("app.js"))
+            .unwrap();
+        let manifest = Manifest::from_json(&fs::read_to_string(&manifest_path).unwrap()).unwrap();
+        assert_eq!(manifest.size("app.js", "identity"), Some(3));
+        assert_eq!(manifest.size("app.js", "gzip"), None);
+    }
+
+    #[test]
+    fn test_out_path_inside_asset_dir() {
+        let dir = tempdir().unwrap();
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        let out_path = asset_dir.join("static_gen.rs");
+        let asset_dirs = [asset_dir.clone()];
 
+        for _ in 0..2 {
+            Config::new().codegen(&out_path, &asset_dirs, &[]).unwrap();
+        }
         let generated = fs::read_to_string(&out_path).unwrap();
+        assert_eq!(generated.matches(" name: \"").count(), 1);
+        assert!(!generated.contains("static_gen_rs"));
 
-        assert!(generated.contains("pub struct StaticFile"));
-        assert!(generated.contains(&format!("file_name: \"{}\"", file_path.to_str().unwrap())));
-        assert!(generated.contains("pub static STATICS: &[&StaticFile]"));
+        // Only the file being written is skipped, not others like it.
+        let manifest_path = asset_dir.join("manifest.json");
+        for _ in 0..2 {
+            Config::new()
+                .write_manifest(&manifest_path, &asset_dirs, &[])
+                .unwrap();
+        }
+        let manifest = fs::read_to_string(&manifest_path).unwrap();
+        assert!(manifest.contains("\"static_gen.rs\""));
+        assert!(!manifest.contains("\"manifest.json\""));
 
-        let hash = format!("{:x}", md5::compute(test_content));
-        assert!(generated.contains(&hash));
+        run_generated(&out_path, "assert_eq!(STATICS.len(), 1);");
     }
 
     #[test]
-    fn test_directory_structure() {
+    fn test_codegen_changed() {
         let dir = tempdir().unwrap();
         let out_path = dir.path().join("static_gen.rs");
+        let manifest_path = dir.path().join("manifest.json");
 
-        let nested_dir = dir.path().join("vendor");
-        fs::create_dir(&nested_dir).unwrap();
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("vendor")).unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        fs::write(asset_dir.join("main.css"), b"main").unwrap();
+        fs::write(asset_dir.join("vendor/lib.js"), b"lib").unwrap();
+        let asset_dirs = [asset_dir.clone()];
+        let changed = asset_dir.join("vendor/lib.js");
 
-        let root_content = b"root file";
-        let nested_content = b"nested file";
+        for config in [
+            Config::new()
+                .etag(HashAlgorithm::Sha256)
+                .routes(true)
+                .match_lookup(true)
+                .resolve(true)
+                .url_macro(true),
+            Config::new()
+                .url_form(UrlForm::DotRelative)
+                .match_lookup(true),
+        ] {
+            fs::write(&changed, b"lib").unwrap();
+            config.codegen(&out_path, &asset_dirs, &[]).unwrap();
+            config
+                .write_manifest(&manifest_path, &asset_dirs, &[])
+                .unwrap();
+            let before = fs::read_to_string(&out_path).unwrap();
+            let manifest_before = fs::read_to_string(&manifest_path).unwrap();
 
-        let (_root_file, root_path) = create_temp_file(root_content, "css");
-        fs::rename(&root_path, dir.path().join("root.css")).unwrap();
+            fs::write(&changed, b"lib v2").unwrap();
+            config
+                .codegen_changed(&out_path, &asset_dirs, &[], &changed)
+                .unwrap();
+            config
+                .write_manifest_changed(&manifest_path, &asset_dirs, &[], &changed)
+                .unwrap();
+            let after = fs::read_to_string(&out_path).unwrap();
+            let manifest_after = fs::read_to_string(&manifest_path).unwrap();
 
-        let nested_path = nested_dir.join("script.js");
-        let mut nested_file = File::create(&nested_path).unwrap();
-        nested_file.write_all(nested_content).unwrap();
+            let old_hash = format!("{:x}", md5::compute(b"lib"));
+            let new_hash = format!("{:x}", md5::compute(b"lib v2"));
+            let changed_lines = before
+                .lines()
+                .zip(after.lines())
+                .filter(|(before, after)| before != after)
+                .collect::<Vec<_>>();
+            assert!(!changed_lines.is_empty());
+            for (before, after) in changed_lines {
+                assert!(before.contains(&old_hash) || before.contains(" etag: "));
+                assert!(after.contains(&new_hash) || after.contains(" etag: "));
+            }
+            assert_eq!(before.lines().count(), after.lines().count());
 
-        codegen(&out_path, &[dir.path().to_path_buf()], &[]).unwrap();
+            // Patching gives the same file as generating it from scratch.
+            config.codegen(&out_path, &asset_dirs, &[]).unwrap();
+            assert_eq!(fs::read_to_string(&out_path).unwrap(), after);
 
-        let generated = fs::read_to_string(&out_path).unwrap();
+            let diff = Manifest::from_json(&manifest_before)
+                .unwrap()
+                .diff(&Manifest::from_json(&manifest_after).unwrap());
+            assert_eq!(diff.changed, ["vendor/lib.js"]);
+            assert!(diff.added.is_empty() && diff.removed.is_empty());
+        }
 
-        assert!(generated.contains("pub mod vendor {"));
-        assert!(generated.contains("pub static root_css"));
-        assert!(generated.contains("pub static script_js"));
+        // Other files aren't rescanned, but a file that isn't in the output
+        // yet falls back to generating everything.
+        let config = Config::new();
+        config.codegen(&out_path, &asset_dirs, &[]).unwrap();
+        fs::write(asset_dir.join("new.js"), b"new").unwrap();
+        config
+            .codegen_changed(&out_path, &asset_dirs, &[], &changed)
+            .unwrap();
+        assert!(!fs::read_to_string(&out_path).unwrap().contains("new_js"));
+        config
+            .codegen_changed(&out_path, &asset_dirs, &[], &asset_dir.join("new.js"))
+            .unwrap();
+        assert!(fs::read_to_string(&out_path).unwrap().contains("new_js"));
 
-        assert!(generated.contains("\"/static/root-"));
-        assert!(generated.contains("\"/static/vendor/script-"));
+        run_generated(&out_path, "assert_eq!(STATICS.len(), 4);");
 
-        assert!(generated.contains("&root_css"));
-        assert!(generated.contains("&vendor::script_js"));
+        // A sniffed MIME type follows the file's new signature.
+        let config = Config::new().sniff_mime(true);
+        let image = asset_dir.join("image");
+        fs::write(&image, b"\x89PNG\r\n\x1a\n").unwrap();
+        config.codegen(&out_path, &asset_dirs, &[]).unwrap();
+        assert!(fs::read_to_string(&out_path)
+            .unwrap()
+            .contains("mime: \"image/png\""));
+        fs::write(&image, b"\xff\xd8\xff\xe0").unwrap();
+        config
+            .codegen_changed(&out_path, &asset_dirs, &[], &image)
+            .unwrap();
+        let patched = fs::read_to_string(&out_path).unwrap();
+        assert!(patched.contains("mime: \"image/jpeg\""));
+        config.codegen(&out_path, &asset_dirs, &[]).unwrap();
+        assert_eq!(fs::read_to_string(&out_path).unwrap(), patched);
     }
 
     #[test]
-    fn test_mime_type_detection() {
+    fn test_flat() {
         let dir = tempdir().unwrap();
         let out_path = dir.path().join("static_gen.rs");
 
-        let extensions = vec!["svg", "png", "jpg", "css", "js", "wasm", "webp", "unknown"];
-        let mut file_paths = Vec::new();
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("vendor/lib")).unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        fs::write(asset_dir.join("vendor/script.js"), b"script").unwrap();
+        fs::write(asset_dir.join("vendor/lib/index.js"), b"index").unwrap();
 
-        for ext in extensions {
-            let (_file, path) = create_temp_file(format!("content for {}", ext).as_bytes(), ext);
-            file_paths.push(path);
-        }
+        Config::new()
+            .flat(true)
+            .codegen(&out_path, std::slice::from_ref(&asset_dir), &[])
+            .unwrap();
 
-        codegen(&out_path, &[], &file_paths).unwrap();
+        let generated = fs::read_to_string(&out_path).unwrap();
+        assert!(!generated.contains("pub mod"));
+        let script = format!("/static/vendor/script-{:x}.js", md5::compute(b"script"));
+        run_generated(
+            &out_path,
+            &format!(
+                r#"
+    assert_eq!(vendor_script_js.name, {script:?});
+    assert!(STATICS.contains(&&vendor_lib_index_js));
+    assert!(STATICS.contains(&&app_js));
+"#
+            ),
+        );
+
+        fs::create_dir_all(asset_dir.join("app")).unwrap();
+        fs::write(asset_dir.join("app/js"), b"js").unwrap();
+        let err = Config::new()
+            .flat(true)
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("`app_js`"));
+    }
+
+    #[test]
+    fn test_overlay() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
 
+        let base = dir.path().join("base");
+        let theme = dir.path().join("theme");
+        fs::create_dir_all(base.join("css")).unwrap();
+        fs::create_dir_all(theme.join("css")).unwrap();
+        fs::write(base.join("logo.png"), b"logo").unwrap();
+        fs::write(base.join("css/app.css"), b"base").unwrap();
+        fs::write(base.join("css/print.css"), b"print").unwrap();
+        fs::write(theme.join("css/app.css"), b"theme").unwrap();
+        fs::write(theme.join("css/dark.css"), b"dark").unwrap();
+        let asset_dirs = [base, theme.clone()];
+
+        // Side by side, both directories have a `css` module.
+        assert!(codegen(&out_path, &asset_dirs, &[]).is_err());
+
+        Config::new()
+            .overlay(true)
+            .codegen(&out_path, &asset_dirs, &[])
+            .unwrap();
         let generated = fs::read_to_string(&out_path).unwrap();
+        assert_eq!(generated.matches("pub mod css {").count(), 1);
+        assert_eq!(generated.matches("pub static app_css").count(), 1);
+        assert!(generated.contains(&format!(
+            "name: \"/static/css/app-{:x}.css\"",
+            md5::compute(b"theme")
+        )));
+        assert!(generated.contains(&format!(
+            "file_name: {:?}",
+            fs::canonicalize(theme.join("css/app.css")).unwrap()
+        )));
+        for name in ["logo_png", "print_css", "dark_css"] {
+            assert!(generated.contains(&format!("pub static {name}: StaticFile")));
+        }
 
-        assert!(generated.contains("mime: \"image/svg+xml\""));
-        assert!(generated.contains("mime: \"image/png\""));
-        assert!(generated.contains("mime: \"image/jpeg\""));
-        assert!(generated.contains("mime: \"text/css\""));
-        assert!(generated.contains("mime: \"application/javascript\""));
-        assert!(generated.contains("mime: \"application/wasm\""));
-        assert!(generated.contains("mime: \"image/webp\""));
-        assert!(generated.contains("mime: \"application/octet-stream\""));
+        run_generated(
+            &out_path,
+            r#"
+    assert_eq!(STATICS.len(), 4);
+    assert!(StaticFile::get(css::app_css.name).is_some());
+"#,
+        );
     }
 
     #[test]
-    fn test_display_implementation() {
+    fn test_reexport() {
         let dir = tempdir().unwrap();
         let out_path = dir.path().join("static_gen.rs");
 
-        let test_content = b"test content for display";
-        let (_file, file_path) = create_temp_file(test_content, "txt");
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("vendor/lib")).unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        fs::write(asset_dir.join("vendor/script.js"), b"script").unwrap();
+        fs::write(asset_dir.join("vendor/lib/index.js"), b"index").unwrap();
 
-        codegen(&out_path, &[], &[file_path]).unwrap();
+        Config::new()
+            .reexport(true)
+            .codegen(&out_path, std::slice::from_ref(&asset_dir), &[])
+            .unwrap();
 
         let generated = fs::read_to_string(&out_path).unwrap();
-        assert!(generated.contains("impl std::fmt::Display for StaticFile"));
-        assert!(generated
-            .contains("fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result"));
+        assert!(generated.contains("pub use vendor::script_js;"));
+        run_generated(
+            &out_path,
+            r#"
+    assert!(std::ptr::eq(&script_js, &vendor::script_js));
+    assert!(std::ptr::eq(&index_js, &vendor::lib::index_js));
+    assert!(std::ptr::eq(&app_js, STATICS[0]));
+"#,
+        );
+
+        fs::write(asset_dir.join("vendor/app.js"), b"vendor app").unwrap();
+        let err = Config::new()
+            .reexport(true)
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("re-exported as `app_js`"));
     }
 
     #[test]
-    fn test_get_method() {
+    fn test_module_collision() {
         let dir = tempdir().unwrap();
         let out_path = dir.path().join("static_gen.rs");
 
-        let test_content = b"test content for get method";
-        let (_file, file_path) = create_temp_file(test_content, "png");
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("a.b")).unwrap();
+        fs::create_dir_all(asset_dir.join("a-b")).unwrap();
+        fs::write(asset_dir.join("a.b/app.js"), b"dot").unwrap();
+        fs::write(asset_dir.join("a-b/app.js"), b"dash").unwrap();
 
-        codegen(&out_path, &[], &[file_path]).unwrap();
+        let err = codegen(&out_path, &[asset_dir], &[]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("module `a_b`"));
+    }
+
+    #[test]
+    fn test_eq_and_hash() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+        fs::write(asset_dir.join("main.css"), b"main").unwrap();
+
+        codegen(&out_path, &[asset_dir], &[]).unwrap();
+
+        run_generated(
+            &out_path,
+            r#"
+    use std::collections::HashSet;
+    use std::hash::{BuildHasher, RandomState};
+
+    let looked_up = StaticFile::get(app_js.name).unwrap();
+    assert_eq!(looked_up, &app_js);
+    assert_ne!(&app_js, &main_css);
+
+    let state = RandomState::new();
+    assert_eq!(state.hash_one(looked_up), state.hash_one(&app_js));
+
+    let set: HashSet<&StaticFile> = [&app_js, looked_up, &main_css].into_iter().collect();
+    assert_eq!(set.len(), 2);
+"#,
+        );
+    }
+
+    #[test]
+    fn test_static_file_path() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        let nested_dir = asset_dir.join("vendor").join("lib");
+        fs::create_dir_all(&nested_dir).unwrap();
+        fs::write(nested_dir.join("script.js"), b"script").unwrap();
+
+        Config::new()
+            .static_file_path("crate::web::assets::StaticFile")
+            .codegen(&out_path, &[asset_dir], &[])
+            .unwrap();
 
         let generated = fs::read_to_string(&out_path).unwrap();
-        assert!(generated.contains("pub fn get(name: &str) -> Option<&'static Self>"));
+        assert!(generated.contains("        use crate::web::assets::StaticFile;"));
+        assert!(!generated.contains("use super::StaticFile;"));
+
+        run_program(&format!(
+            r#"
+mod web {{
+    pub mod assets {{
+        include!({:?});
+    }}
+}}
+
+fn main() {{
+    let file: &web::assets::StaticFile = &web::assets::vendor::lib::script_js;
+    assert!(web::assets::StaticFile::get(file.name).is_some());
+}}
+"#,
+            out_path
+        ));
     }
 }
@@ -0,0 +1,501 @@
+//! The generated statics as tokens, for proc macros instead of `include!`.
+
+use std::path::PathBuf;
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+
+use crate::{generate, Config, Field, StaticRecord, UrlForm};
+
+impl Config {
+    /// Generate the statics as a [`TokenStream`], eg. for a proc macro to
+    /// expand into, rather than writing a file for `include!`. The items are
+    /// built with `quote!`, so paths, URLs and other values from the assets
+    /// are literal tokens rather than escaped source text.
+    ///
+    /// The tokens cover the statics, their modules, `STATICS` and the
+    /// `StaticFile` methods for options that only change each static's
+    /// values. Options generating further items, like [`Config::axum`] or
+    /// [`Config::kinds`], fail with [`std::io::ErrorKind::InvalidInput`];
+    /// use [`Config::codegen`] for those. Layout options like
+    /// [`Config::hoist_bytes`] and [`Config::header`] don't apply.
+    pub fn token_stream(
+        &self,
+        asset_dirs: &[PathBuf],
+        extra_files: &[PathBuf],
+    ) -> std::io::Result<TokenStream> {
+        if let Some(option) = self.unsupported_by_token_stream() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{option} isn't supported by Config::token_stream, see Config::codegen"),
+            ));
+        }
+        let (_, output) = generate(self, asset_dirs, extra_files, None)?;
+        Ok(tokens(self, &output.records))
+    }
+
+    /// The first option set that [`Config::token_stream`] can't build the
+    /// items of, if any.
+    fn unsupported_by_token_stream(&self) -> Option<&'static str> {
+        [
+            ("Config::cfg", !self.cfgs.is_empty()),
+            (
+                "Config::public_url_prefix",
+                self.public_url_prefix.is_some(),
+            ),
+            ("Config::index_files", self.index_files),
+            ("Config::url_form", self.url_form != UrlForm::Absolute),
+            ("Config::reexport", self.reexport),
+            ("Config::categories", self.categories),
+            ("Config::dir_hashes", self.dir_hashes),
+            ("Config::generated_at", self.generated_at),
+            ("Config::const_refs", self.const_refs),
+            ("Config::asset_enum", self.asset_enum),
+            ("Config::alternates", self.alternates),
+            ("Config::source_maps", self.source_maps),
+            (
+                "Config::rewrite_source_map_urls",
+                self.rewrite_source_map_urls,
+            ),
+            ("Config::match_lookup", self.match_lookup),
+            ("Config::lazy_lookup", self.lazy_lookup),
+            ("Config::resolve", self.resolve),
+            ("Config::url_macro", self.url_macro),
+            ("Config::fallback", self.fallback.is_some()),
+            ("Config::not_found_body", self.not_found.is_some()),
+            ("Config::routes", self.routes),
+            ("Config::static_file_path", self.static_file_path.is_some()),
+            ("Config::base_url_env", self.base_url_env.is_some()),
+            ("Config::serve_root", self.serve_root),
+            ("Config::kinds", self.kinds),
+            ("Config::gzip", self.gzip_enabled()),
+            ("Config::brotli", self.brotli_enabled()),
+            (
+                "Config::compress_embedded",
+                self.compress_embedded_enabled(),
+            ),
+            ("Config::axum", self.axum_enabled()),
+            ("Config::rocket", self.rocket_enabled()),
+            ("Config::phf_lookup", self.phf_lookup_enabled()),
+            ("Config::http", self.http_enabled()),
+        ]
+        .into_iter()
+        .find(|(_, enabled)| *enabled)
+        .map(|(option, _)| option)
+    }
+}
+
+/// The statics of one module, in the order they're generated, along with
+/// the modules inside it.
+#[derive(Default)]
+struct Module {
+    entries: Vec<Entry>,
+}
+
+enum Entry {
+    /// A static's doc attributes and the rest of the item.
+    Static(TokenStream, TokenStream),
+    Module(String, Module),
+}
+
+impl Module {
+    /// Add `item` to the module at `path` under this one, creating it if
+    /// needed.
+    fn insert(&mut self, path: &[&str], doc: TokenStream, item: TokenStream) {
+        let Some((name, rest)) = path.split_first() else {
+            self.entries.push(Entry::Static(doc, item));
+            return;
+        };
+        let position = self
+            .entries
+            .iter()
+            .position(|entry| matches!(entry, Entry::Module(other, _) if other == name));
+        let position = position.unwrap_or_else(|| {
+            self.entries
+                .push(Entry::Module(name.to_string(), Module::default()));
+            self.entries.len() - 1
+        });
+        if let Entry::Module(_, module) = &mut self.entries[position] {
+            module.insert(rest, doc, item);
+        }
+    }
+
+    /// This module's items, with the lint allowances top-level items get.
+    fn to_tokens(&self, top_level: bool) -> TokenStream {
+        let lints = top_level.then(lints);
+        let entries = self.entries.iter().map(|entry| match entry {
+            Entry::Static(doc, item) => quote! { #doc #lints #item },
+            Entry::Module(name, module) => {
+                let name = format_ident!("{name}");
+                let items = module.to_tokens(false);
+                quote! {
+                    #lints
+                    pub mod #name {
+                        use super::StaticFile;
+                        #items
+                    }
+                }
+            }
+        });
+        quote! { #(#entries)* }
+    }
+}
+
+/// The lints allowed on top-level items, as in the generated file.
+fn lints() -> TokenStream {
+    quote! { #[allow(clippy::all, clippy::pedantic, clippy::nursery, missing_docs)] }
+}
+
+/// The items for `records`, matching those [`Config::codegen`] writes for
+/// the options [`Config::token_stream`] supports.
+fn tokens(config: &Config, records: &[StaticRecord]) -> TokenStream {
+    let file_name = config.has_field(Field::FileName);
+    let meta = config.has_field(Field::Meta);
+    let cache_control = config.has_field(Field::CacheControl);
+    let hash = config.content_hashes;
+    let etag = config.etag.is_some();
+    let disposition = !config.downloads.is_empty();
+    let preload = !config.preloads.is_empty();
+    let embed = config.embed;
+
+    let mut fields = TokenStream::new();
+    if file_name {
+        fields.extend(quote! { pub file_name: &'static str, });
+    }
+    fields.extend(quote! { pub name: &'static str, pub mime: &'static str, });
+    if meta {
+        fields.extend(quote! { pub meta: &'static [(&'static str, &'static str)], });
+    }
+    if cache_control {
+        fields.extend(quote! { pub cache_control: &'static str, });
+    }
+    if hash {
+        fields.extend(quote! { pub hash: &'static str, });
+    }
+    if etag {
+        fields.extend(quote! { pub etag: &'static str, });
+    }
+    if disposition {
+        fields.extend(quote! { pub disposition: Option<&'static str>, });
+    }
+    if preload {
+        fields.extend(quote! { pub preload: bool, });
+    }
+    if embed {
+        fields.extend(quote! { pub content: &'static [u8], });
+    }
+
+    let mut root = Module::default();
+    let mut references = Vec::new();
+    for record in records {
+        let ident = format_ident!("{}", record.ident);
+        let mut values = TokenStream::new();
+        if file_name {
+            let file_name = &record.file_name;
+            values.extend(quote! { file_name: #file_name, });
+        }
+        let (name, mime) = (&record.name, &record.mime);
+        values.extend(quote! { name: #name, mime: #mime, });
+        if meta {
+            let (keys, values_): (Vec<_>, Vec<_>) = record.meta.iter().cloned().unzip();
+            values.extend(quote! { meta: &[#((#keys, #values_)),*], });
+        }
+        if cache_control {
+            let cache_control = &record.cache_control;
+            values.extend(quote! { cache_control: #cache_control, });
+        }
+        if hash {
+            let hash = &record.hash;
+            values.extend(quote! { hash: #hash, });
+        }
+        if etag {
+            let etag = &record.etag;
+            values.extend(quote! { etag: #etag, });
+        }
+        if disposition {
+            let disposition = match &record.disposition {
+                Some(disposition) => quote! { Some(#disposition) },
+                None => quote! { None },
+            };
+            values.extend(quote! { disposition: #disposition, });
+        }
+        if preload {
+            let preload = record.preload;
+            values.extend(quote! { preload: #preload, });
+        }
+        if embed {
+            let file_name = &record.file_name;
+            values.extend(quote! { content: include_bytes!(#file_name), });
+        }
+
+        let doc = record
+            .doc
+            .iter()
+            .map(|line| format!(" {line}").trim_end().to_string());
+        let path = record
+            .module_path
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>();
+        root.insert(
+            &path,
+            quote! { #(#[doc = #doc])* },
+            quote! {
+                #[allow(non_upper_case_globals)]
+                pub static #ident: StaticFile = StaticFile { #values };
+            },
+        );
+        let modules = path.iter().map(|part| format_ident!("{part}"));
+        references.push(quote! { &#(#modules::)*#ident });
+    }
+
+    let core = format_ident!("{}", if config.no_std { "core" } else { "std" });
+    let validate_sources = (!embed && file_name && !config.no_std).then(|| {
+        quote! {
+            /// Check that every static's file is still on disk, eg. at the start of
+            /// a dev server, returning the `file_name` of each one that's missing.
+            pub fn validate_sources() -> Result<(), Vec<&'static str>> {
+                let missing: Vec<&'static str> = STATICS
+                    .iter()
+                    .filter(|file| !std::path::Path::new(&file.file_name).is_file())
+                    .map(|file| file.file_name)
+                    .collect();
+                if missing.is_empty() {
+                    Ok(())
+                } else {
+                    Err(missing)
+                }
+            }
+        }
+    });
+    let inline = embed.then(|| {
+        quote! {
+            /// This file's MIME type and contents, eg. for inlining critical CSS
+            /// into a page's `<head>`.
+            #[must_use]
+            pub fn inline(&self) -> (&'static str, &'static [u8]) {
+                (self.mime, self.content)
+            }
+        }
+    });
+    let find_by_hash = hash.then(|| {
+        quote! {
+            /// The file whose `hash` is `hash`, or the first in `STATICS` if
+            /// truncated hashes make several share it.
+            #[must_use]
+            pub fn find_by_hash(hash: &str) -> Option<&'static Self> {
+                STATICS.iter().copied().find(|file| file.hash == hash)
+            }
+        }
+    });
+    let preload_link = preload.then(|| {
+        quote! {
+            /// A `<link rel="preload">` tag for this file if it's marked for
+            /// preloading, `None` otherwise.
+            #[must_use]
+            pub fn preload_link(&self) -> Option<String> {
+                if !self.preload {
+                    return None;
+                }
+                let mime = self.mime.split(';').next().unwrap_or_default().trim();
+                let (destination, crossorigin) = match mime {
+                    "text/css" => ("style", ""),
+                    "application/javascript" | "text/javascript" => ("script", ""),
+                    mime if mime.starts_with("font/") => ("font", " crossorigin"),
+                    mime if mime.starts_with("image/") => ("image", ""),
+                    _ => ("fetch", " crossorigin"),
+                };
+                Some(format!(
+                    "<link rel=\"preload\" href=\"{self}\" as=\"{destination}\"{crossorigin}>"
+                ))
+            }
+        }
+    });
+
+    let lints = lints();
+    let derives = config
+        .derive_names()
+        .into_iter()
+        .map(|derive| format_ident!("{derive}"));
+    let modules = root.to_tokens(true);
+    quote! {
+        #lints
+        #[derive(Debug #(, #derives)*)]
+        pub struct StaticFile { #fields }
+
+        #modules
+
+        #lints
+        mod static_file_impls {
+            use super::{StaticFile, STATICS};
+
+            #[allow(dead_code)]
+            impl StaticFile {
+                /// Get a single `StaticFile` by name, if it exists.
+                #[must_use]
+                pub fn get(name: &str) -> Option<&'static Self> {
+                    if let Some(pos) = STATICS.iter().position(|s| s.matches_request(name)) {
+                        Some(STATICS[pos])
+                    } else {
+                        None
+                    }
+                }
+
+                /// Whether a request path refers to this file, ignoring any query string
+                /// or fragment.
+                #[must_use]
+                pub fn matches_request(&self, path: &str) -> bool {
+                    let path = path.find(['?', '#']).map_or(path, |end| &path[..end]);
+                    path == self.name
+                }
+
+                #validate_sources
+                #inline
+                #find_by_hash
+                #preload_link
+            }
+
+            impl #core::fmt::Display for StaticFile {
+                fn fmt(&self, f: &mut #core::fmt::Formatter<'_>) -> #core::fmt::Result {
+                    write!(f, "{}", self.name)
+                }
+            }
+
+            impl PartialEq for StaticFile {
+                fn eq(&self, other: &Self) -> bool {
+                    self.name == other.name
+                }
+            }
+
+            impl Eq for StaticFile {}
+
+            impl #core::hash::Hash for StaticFile {
+                fn hash<H: #core::hash::Hasher>(&self, state: &mut H) {
+                    self.name.hash(state);
+                }
+            }
+        }
+
+        #lints
+        pub static STATICS: &[&StaticFile] = &[#(#references,)*];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quote::ToTokens;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_token_stream() {
+        let dir = tempdir().unwrap();
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("app")).unwrap();
+        fs::write(asset_dir.join("app/main.js"), b"main").unwrap();
+        fs::write(asset_dir.join("odd \"name\".css"), b"odd").unwrap();
+
+        let tokens = Config::new()
+            .etag(crate::HashAlgorithm::Md5)
+            .token_stream(&[asset_dir], &[])
+            .unwrap();
+
+        let file = syn::parse2::<syn::File>(tokens).unwrap();
+        let names = file
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                syn::Item::Static(item) => Some(item.ident.to_string()),
+                syn::Item::Mod(item) => Some(item.ident.to_string()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert!(names.contains(&"app".to_string()));
+        assert!(names.contains(&"odd_name_css".to_string()));
+        assert!(names.contains(&"STATICS".to_string()));
+    }
+
+    /// `tokens` as text with string literals written the same way whatever
+    /// their source form, eg. doc comments that `quote!` makes raw strings.
+    fn normalized(tokens: TokenStream) -> String {
+        tokens
+            .into_iter()
+            .map(|token| match token {
+                proc_macro2::TokenTree::Group(group) => {
+                    let mut normalized = proc_macro2::Group::new(
+                        group.delimiter(),
+                        normalized(group.stream()).parse().unwrap(),
+                    );
+                    normalized.set_span(group.span());
+                    proc_macro2::TokenTree::Group(normalized)
+                }
+                proc_macro2::TokenTree::Literal(literal) => {
+                    match syn::parse2::<syn::LitStr>(literal.to_token_stream()) {
+                        Ok(string) => proc_macro2::Literal::string(&string.value()).into(),
+                        Err(_) => literal.into(),
+                    }
+                }
+                token => token,
+            })
+            .collect::<TokenStream>()
+            .to_string()
+    }
+
+    #[test]
+    fn test_token_stream_matches_codegen() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("app/admin")).unwrap();
+        fs::write(asset_dir.join("app/main.js"), b"main").unwrap();
+        fs::write(asset_dir.join("app/admin/back\\slash.js"), b"admin").unwrap();
+        fs::write(asset_dir.join("café \"menu\".pdf"), b"%PDF").unwrap();
+        let extra_file = dir.path().join("LICENSE");
+        fs::write(&extra_file, b"license").unwrap();
+
+        let configs = [
+            Config::new(),
+            Config::new()
+                .embed(true)
+                .etag(crate::HashAlgorithm::Md5)
+                .content_hashes(true)
+                .download("*.pdf")
+                .preload("app/*")
+                .meta("app/**", "owner", "team \"web\"")
+                .doc_template("{path}\nat {url}"),
+            Config::new()
+                .flat(true)
+                .no_std(true)
+                .derives([crate::Derive::Clone, crate::Derive::Copy]),
+        ];
+        for config in configs {
+            config
+                .codegen(
+                    &out_path,
+                    std::slice::from_ref(&asset_dir),
+                    std::slice::from_ref(&extra_file),
+                )
+                .unwrap();
+            let written = syn::parse_file(&fs::read_to_string(&out_path).unwrap()).unwrap();
+            let tokens = config
+                .token_stream(
+                    std::slice::from_ref(&asset_dir),
+                    std::slice::from_ref(&extra_file),
+                )
+                .unwrap();
+            let built = syn::parse2::<syn::File>(tokens).unwrap();
+            assert_eq!(
+                normalized(built.to_token_stream()),
+                normalized(written.to_token_stream())
+            );
+        }
+
+        let err = Config::new()
+            .kinds(true)
+            .token_stream(&[asset_dir], &[])
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("Config::kinds"));
+    }
+}
@@ -0,0 +1,403 @@
+//! The JSON manifest mapping asset paths to hashed URLs, as written by
+//! [`Config::manifest`](crate::Config::manifest), and diffing two of them.
+
+use std::collections::BTreeMap;
+
+/// Every file's path, relative to its asset directory, and its hashed URL,
+/// along with its sizes if [recorded](crate::Config::manifest_sizes).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    entries: BTreeMap<String, String>,
+    /// Each file's size in bytes by content encoding, eg. `gzip`.
+    sizes: BTreeMap<String, BTreeMap<String, u64>>,
+}
+
+/// How one [`Manifest`] differs from an earlier one, as sorted lists of
+/// asset paths.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ManifestDiff {
+    /// Paths only in the later manifest.
+    pub added: Vec<String>,
+    /// Paths only in the earlier manifest.
+    pub removed: Vec<String>,
+    /// Paths in both whose URL changed.
+    pub changed: Vec<String>,
+}
+
+impl ManifestDiff {
+    /// Whether the manifests are identical.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl Manifest {
+    pub(crate) fn new(entries: BTreeMap<String, String>) -> Self {
+        Self {
+            entries,
+            sizes: BTreeMap::new(),
+        }
+    }
+
+    /// Set the URL of `path`, forgetting any sizes it had.
+    pub(crate) fn insert(&mut self, path: String, url: String) {
+        self.sizes.remove(&path);
+        self.entries.insert(path, url);
+    }
+
+    pub(crate) fn insert_size(&mut self, path: &str, encoding: &str, size: u64) {
+        self.sizes
+            .entry(path.to_string())
+            .or_default()
+            .insert(encoding.to_string(), size);
+    }
+
+    /// Parse a manifest previously written by
+    /// [`Config::manifest`](crate::Config::manifest).
+    pub fn from_json(json: &str) -> std::io::Result<Self> {
+        let mut parser = Parser {
+            chars: json.chars().collect(),
+            pos: 0,
+        };
+        let mut manifest = Self::default();
+
+        parser.object(|parser, path| {
+            if !parser.peek('{') {
+                let url = parser.string()?;
+                manifest.entries.insert(path, url);
+                return Ok(());
+            }
+
+            let mut url = None;
+            parser.object(|parser, key| match key.as_str() {
+                "url" => {
+                    url = Some(parser.string()?);
+                    Ok(())
+                }
+                "sizes" => parser.object(|parser, encoding| {
+                    let size = parser.number()?;
+                    manifest.insert_size(&path, &encoding, size);
+                    Ok(())
+                }),
+                _ => Err(parser.error(&format!("unknown key `{key}`"))),
+            })?;
+            let url = url.ok_or_else(|| parser.error("missing `url`"))?;
+            manifest.entries.insert(path, url);
+            Ok(())
+        })?;
+        parser.skip_whitespace();
+        if parser.pos < parser.chars.len() {
+            return Err(parser.error("trailing characters"));
+        }
+
+        Ok(manifest)
+    }
+
+    /// The manifest as a JSON object, one entry per line with sorted keys.
+    /// Files with sizes map to an object of their `url` and `sizes`.
+    pub fn to_json(&self) -> String {
+        let entries = self
+            .entries
+            .iter()
+            .map(|(path, url)| match self.sizes.get(path) {
+                Some(sizes) => {
+                    let sizes = sizes
+                        .iter()
+                        .map(|(encoding, size)| format!("{}: {size}", json_string(encoding)))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    format!(
+                        "  {}: {{\"url\": {}, \"sizes\": {{{sizes}}}}}",
+                        json_string(path),
+                        json_string(url)
+                    )
+                }
+                None => format!("  {}: {}", json_string(path), json_string(url)),
+            })
+            .collect::<Vec<_>>();
+
+        if entries.is_empty() {
+            "{}\n".to_string()
+        } else {
+            format!("{{\n{}\n}}\n", entries.join(",\n"))
+        }
+    }
+
+    /// The URL of the file at `path`, relative to its asset directory.
+    pub fn get(&self, path: &str) -> Option<&str> {
+        self.entries.get(path).map(String::as_str)
+    }
+
+    /// The size in bytes of the file at `path` as served with the content
+    /// `encoding`, eg. `identity`, `gzip` or `br`, if recorded.
+    pub fn size(&self, path: &str, encoding: &str) -> Option<u64> {
+        self.sizes.get(path)?.get(encoding).copied()
+    }
+
+    /// Every path and URL, sorted by path.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .map(|(path, url)| (path.as_str(), url.as_str()))
+    }
+
+    /// What changed from this manifest to `later`, eg. to find the URLs to
+    /// invalidate on a CDN after a deploy.
+    pub fn diff(&self, later: &Manifest) -> ManifestDiff {
+        let mut diff = ManifestDiff::default();
+
+        for (path, url) in &self.entries {
+            match later.entries.get(path) {
+                None => diff.removed.push(path.clone()),
+                Some(later_url) if later_url != url => diff.changed.push(path.clone()),
+                Some(_) => {}
+            }
+        }
+        for path in later.entries.keys() {
+            if !self.entries.contains_key(path) {
+                diff.added.push(path.clone());
+            }
+        }
+
+        diff
+    }
+}
+
+/// `value` as a JSON string literal.
+fn json_string(value: &str) -> String {
+    let mut literal = String::with_capacity(value.len() + 2);
+    literal.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            c if c < ' ' => literal.push_str(&format!("\\u{:04x}", c as u32)),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// Just enough of a JSON parser for a manifest.
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> std::io::Error {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("invalid manifest at character {}: {}", self.pos, message),
+        )
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.get(self.pos).is_some_and(|c| c.is_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        self.skip_whitespace();
+        if self.chars.get(self.pos) == Some(&expected) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn peek(&mut self, expected: char) -> bool {
+        self.skip_whitespace();
+        self.chars.get(self.pos) == Some(&expected)
+    }
+
+    /// Parse an object, calling `value` to parse the value of each key.
+    fn object(
+        &mut self,
+        mut value: impl FnMut(&mut Self, String) -> std::io::Result<()>,
+    ) -> std::io::Result<()> {
+        self.expect('{')?;
+        if self.eat('}') {
+            return Ok(());
+        }
+        loop {
+            let key = self.string()?;
+            self.expect(':')?;
+            value(self, key)?;
+
+            if self.eat('}') {
+                return Ok(());
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn number(&mut self) -> std::io::Result<u64> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while self.chars.get(self.pos).is_some_and(char::is_ascii_digit) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .map_err(|_| self.error("expected a size"))
+    }
+
+    fn expect(&mut self, expected: char) -> std::io::Result<()> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{expected}`")))
+        }
+    }
+
+    fn next(&mut self) -> std::io::Result<char> {
+        let c = self
+            .chars
+            .get(self.pos)
+            .copied()
+            .ok_or_else(|| self.error("unexpected end"))?;
+        self.pos += 1;
+        Ok(c)
+    }
+
+    fn hex_escape(&mut self) -> std::io::Result<u32> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = self.next()?.to_digit(16);
+            code = code * 16 + digit.ok_or_else(|| self.error("invalid \\u escape"))?;
+        }
+        Ok(code)
+    }
+
+    fn string(&mut self) -> std::io::Result<String> {
+        self.expect('"')?;
+        let mut value = String::new();
+        loop {
+            match self.next()? {
+                '"' => return Ok(value),
+                '\\' => {
+                    let c = match self.next()? {
+                        '"' => '"',
+                        '\\' => '\\',
+                        '/' => '/',
+                        'b' => '\u{8}',
+                        'f' => '\u{c}',
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        'u' => {
+                            let mut code = self.hex_escape()?;
+                            if (0xd800..0xdc00).contains(&code) {
+                                if self.next()? != '\\' || self.next()? != 'u' {
+                                    return Err(self.error("unpaired surrogate"));
+                                }
+                                let low = self.hex_escape()?;
+                                if !(0xdc00..0xe000).contains(&low) {
+                                    return Err(self.error("unpaired surrogate"));
+                                }
+                                code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                            }
+                            char::from_u32(code).ok_or_else(|| self.error("invalid \\u escape"))?
+                        }
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    value.push(c);
+                }
+                c => value.push(c),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(entries: &[(&str, &str)]) -> Manifest {
+        Manifest::new(
+            entries
+                .iter()
+                .map(|(path, url)| (path.to_string(), url.to_string()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let original = manifest(&[
+            ("app.js", "/static/app-1a2b.js"),
+            ("odd \"name\"\\\t.css", "/static/odd-3c4d.css"),
+        ]);
+        let json = original.to_json();
+        assert_eq!(Manifest::from_json(&json).unwrap(), original);
+
+        assert_eq!(Manifest::from_json(" {} ").unwrap(), Manifest::default());
+        assert_eq!(
+            Manifest::from_json(r#"{"café😀": "\/static"}"#)
+                .unwrap()
+                .get("café😀"),
+            Some("/static")
+        );
+        assert_eq!(
+            Manifest::from_json(r#"{"\ud83d\ude00": ""}"#)
+                .unwrap()
+                .get("😀"),
+            Some("")
+        );
+        assert!(Manifest::from_json(r#"{"\ud83d\u0041": ""}"#).is_err());
+        assert!(Manifest::from_json(r#"{"a": "b",}"#).is_err());
+        assert!(Manifest::from_json(r#"{"a": "b"} x"#).is_err());
+    }
+
+    #[test]
+    fn test_sizes_round_trip() {
+        let mut original = manifest(&[
+            ("app.js", "/static/app-1a2b.js"),
+            ("logo.png", "/static/logo.png"),
+        ]);
+        original.insert_size("app.js", "identity", 1024);
+        original.insert_size("app.js", "gzip", 340);
+        let json = original.to_json();
+        assert!(json.contains(
+            r#""app.js": {"url": "/static/app-1a2b.js", "sizes": {"gzip": 340, "identity": 1024}}"#
+        ));
+
+        let parsed = Manifest::from_json(&json).unwrap();
+        assert_eq!(parsed, original);
+        assert_eq!(parsed.get("app.js"), Some("/static/app-1a2b.js"));
+        assert_eq!(parsed.size("app.js", "gzip"), Some(340));
+        assert_eq!(parsed.size("app.js", "br"), None);
+        assert_eq!(parsed.size("logo.png", "identity"), None);
+
+        assert!(Manifest::from_json(r#"{"a": {"sizes": {}}}"#).is_err());
+        assert!(Manifest::from_json(r#"{"a": {"url": "b", "size": 1}}"#).is_err());
+        assert!(Manifest::from_json(r#"{"a": {"url": "b", "sizes": {"gzip": -1}}}"#).is_err());
+    }
+
+    #[test]
+    fn test_diff() {
+        let before = manifest(&[
+            ("app.js", "/static/app-1.js"),
+            ("main.css", "/static/main-1.css"),
+            ("old.png", "/static/old-1.png"),
+        ]);
+        let after = manifest(&[
+            ("app.js", "/static/app-2.js"),
+            ("main.css", "/static/main-1.css"),
+            ("new.png", "/static/new-1.png"),
+        ]);
+
+        let diff = before.diff(&after);
+        assert_eq!(diff.added, ["new.png"]);
+        assert_eq!(diff.removed, ["old.png"]);
+        assert_eq!(diff.changed, ["app.js"]);
+        assert!(before.diff(&before).is_empty());
+    }
+}
@@ -0,0 +1,173 @@
+//! Reverse proxy configuration caching the statics as they're generated, for
+//! apps fronted by Nginx or Caddy.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use crate::{generate, slashed_prefix, Config, IMMUTABLE};
+
+/// The reverse proxy [`Config::proxy_config`] writes configuration for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyServer {
+    /// `location` blocks for a `server` block.
+    Nginx,
+    /// Matchers and `header` directives for a site block.
+    Caddy,
+}
+
+impl Config {
+    /// A snippet for `server`'s configuration setting `Cache-Control` on the
+    /// statics, eg. to serve them from the proxy rather than the app. URLs
+    /// under the URL prefixes are `immutable`, except for files whose
+    /// [`cache_control`](Config::cache_control) differs, like HTML and
+    /// [unhashed](Config::no_hash) files, which get their own value.
+    pub fn proxy_config(
+        &self,
+        server: ProxyServer,
+        asset_dirs: &[PathBuf],
+        extra_files: &[PathBuf],
+    ) -> std::io::Result<String> {
+        let (_, output) = generate(self, asset_dirs, extra_files, None)?;
+
+        let prefixes = self
+            .url_prefixes
+            .iter()
+            .map(|(_, prefix)| prefix.as_str())
+            .chain([self.url_prefix.as_deref().unwrap_or("/static/")])
+            .map(slashed_prefix)
+            .collect::<BTreeSet<_>>();
+
+        // URLs by the `Cache-Control` they need instead of `immutable`.
+        let mut exceptions = BTreeMap::<&str, Vec<String>>::new();
+        for (rel_path, url) in output.manifest.iter() {
+            let extension = Path::new(url)
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .unwrap_or_default();
+            let cache_control = self.cache_control_for(rel_path, extension);
+            if cache_control != IMMUTABLE {
+                let url = url.trim_start_matches("./").trim_start_matches('/');
+                exceptions
+                    .entry(cache_control)
+                    .or_default()
+                    .push(format!("/{url}"));
+            }
+        }
+
+        let mut snippet = String::from("# @generated by cacheb\n");
+        match server {
+            ProxyServer::Nginx => {
+                let blocks = prefixes
+                    .iter()
+                    .map(|prefix| (quote(prefix), IMMUTABLE))
+                    .chain(exceptions.iter().flat_map(|(cache_control, urls)| {
+                        urls.iter()
+                            .map(move |url| (format!("= {}", quote(url)), *cache_control))
+                    }));
+                for (location, cache_control) in blocks {
+                    snippet.push_str(&format!(
+                        "location {location} {{\n    add_header Cache-Control {};\n}}\n",
+                        quote(cache_control)
+                    ));
+                }
+            }
+            ProxyServer::Caddy => {
+                let excepted = exceptions
+                    .values()
+                    .flatten()
+                    .map(|url| quote(url))
+                    .collect::<Vec<_>>();
+                snippet.push_str("@cacheb_immutable {\n");
+                for prefix in &prefixes {
+                    snippet.push_str(&format!("    path {}\n", quote(&format!("{prefix}*"))));
+                }
+                if !excepted.is_empty() {
+                    snippet.push_str(&format!("    not path {}\n", excepted.join(" ")));
+                }
+                snippet.push_str(&format!(
+                    "}}\nheader @cacheb_immutable Cache-Control {}\n",
+                    quote(IMMUTABLE)
+                ));
+
+                for (index, (cache_control, urls)) in exceptions.iter().enumerate() {
+                    let urls = urls.iter().map(|url| quote(url)).collect::<Vec<_>>();
+                    snippet.push_str(&format!(
+                        "@cacheb_{index} path {}\nheader @cacheb_{index} Cache-Control {}\n",
+                        urls.join(" "),
+                        quote(cache_control)
+                    ));
+                }
+            }
+        }
+        Ok(snippet)
+    }
+}
+
+/// `value` as a double-quoted string, which both Nginx and Caddy accept.
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_proxy_config() {
+        let dir = tempdir().unwrap();
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir_all(asset_dir.join("app")).unwrap();
+        fs::write(asset_dir.join("app/main.js"), b"main").unwrap();
+        fs::write(asset_dir.join("index.html"), b"<html>").unwrap();
+        fs::write(asset_dir.join("favicon.ico"), b"icon").unwrap();
+
+        let config = Config::new()
+            .url_prefix("/assets")
+            .no_hash("favicon.ico")
+            .cache_control("index.html", "max-age=60");
+        let asset_dirs = [asset_dir];
+        let index = format!("/assets/index-{:x}.html", md5::compute(b"<html>"));
+
+        let nginx = config
+            .proxy_config(ProxyServer::Nginx, &asset_dirs, &[])
+            .unwrap();
+        assert_eq!(
+            nginx,
+            format!(
+                r#"# @generated by cacheb
+location "/assets/" {{
+    add_header Cache-Control "public, max-age=31536000, immutable";
+}}
+location = "{index}" {{
+    add_header Cache-Control "max-age=60";
+}}
+location = "/assets/favicon.ico" {{
+    add_header Cache-Control "no-cache";
+}}
+"#
+            )
+        );
+
+        let caddy = config
+            .proxy_config(ProxyServer::Caddy, &asset_dirs, &[])
+            .unwrap();
+        assert_eq!(
+            caddy,
+            format!(
+                r#"# @generated by cacheb
+@cacheb_immutable {{
+    path "/assets/*"
+    not path "{index}" "/assets/favicon.ico"
+}}
+header @cacheb_immutable Cache-Control "public, max-age=31536000, immutable"
+@cacheb_0 path "{index}"
+header @cacheb_0 Cache-Control "max-age=60"
+@cacheb_1 path "/assets/favicon.ico"
+header @cacheb_1 Cache-Control "no-cache"
+"#
+            )
+        );
+    }
+}
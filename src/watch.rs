@@ -0,0 +1,105 @@
+//! Regenerating the statics file as assets change, for dev servers and tools.
+
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+use notify::{RecursiveMode, Watcher};
+
+use crate::Config;
+
+/// How long the asset directories must be quiet before regenerating, so a
+/// burst of changes like a `git checkout` only triggers one run.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+impl Config {
+    /// Generate the statics file at `out_path`, then regenerate it whenever
+    /// anything under `asset_dirs` or in `extra_files` changes. The file is
+    /// only rewritten when its contents differ.
+    ///
+    /// Blocks the calling thread, returning only if watching fails. Errors
+    /// from individual regenerations are printed to stderr and watching
+    /// continues, so a half-written asset doesn't end the session.
+    pub fn watch(
+        &self,
+        out_path: &Path,
+        asset_dirs: &[PathBuf],
+        extra_files: &[PathBuf],
+    ) -> std::io::Result<()> {
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender).map_err(std::io::Error::other)?;
+
+        for asset_dir in asset_dirs {
+            watcher
+                .watch(asset_dir, RecursiveMode::Recursive)
+                .map_err(std::io::Error::other)?;
+        }
+        for file_path in extra_files {
+            watcher
+                .watch(file_path, RecursiveMode::NonRecursive)
+                .map_err(std::io::Error::other)?;
+        }
+
+        self.codegen(out_path, asset_dirs, extra_files)?;
+
+        while let Ok(event) = receiver.recv() {
+            let event = event.map_err(std::io::Error::other)?;
+            if event.kind.is_access() {
+                continue;
+            }
+
+            while receiver.recv_timeout(DEBOUNCE).is_ok() {}
+
+            if let Err(err) = self.codegen(out_path, asset_dirs, extra_files) {
+                eprintln!(
+                    "cacheb: failed to regenerate {}: {}",
+                    out_path.display(),
+                    err
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::thread;
+    use std::time::Instant;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_watch_regenerates() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("static_gen.rs");
+
+        let asset_dir = dir.path().join("assets");
+        fs::create_dir(&asset_dir).unwrap();
+        fs::write(asset_dir.join("app.js"), b"app").unwrap();
+
+        {
+            let out_path = out_path.clone();
+            let asset_dir = asset_dir.clone();
+            thread::spawn(move || Config::new().watch(&out_path, &[asset_dir], &[]));
+        }
+
+        let wait_for = |needle: &str| {
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while Instant::now() < deadline {
+                if fs::read_to_string(&out_path).is_ok_and(|generated| generated.contains(needle)) {
+                    return;
+                }
+                thread::sleep(Duration::from_millis(20));
+            }
+            panic!("{} never appeared in the generated file", needle);
+        };
+
+        wait_for("pub static app_js");
+
+        fs::write(asset_dir.join("main.css"), b"body {}").unwrap();
+        wait_for("pub static main_css");
+    }
+}